@@ -0,0 +1,130 @@
+use std::f32::consts::PI;
+
+use num::complex::Complex32;
+
+pub const BELL202_BAUD: f32 = 1200.0;
+pub const BELL202_MARK: f32 = 1200.0;
+pub const BELL202_SPACE: f32 = 2200.0;
+
+/// Bell-202 tone demodulator for (FM demodulated) audio.
+///
+/// Correlates the last bit period with the mark and space tones
+/// and returns `(mark - space) / (mark + space)`, i.e. positive values
+/// for the mark tone, independent of the audio level.
+#[derive(Debug)]
+pub struct Bell202Demodulator {
+    mark_ref: Vec<Complex32>,
+    space_ref: Vec<Complex32>,
+    history: Vec<f32>,
+    history_idx: usize,
+}
+
+impl Bell202Demodulator {
+    pub fn new(sample_rate: f32) -> Self {
+        let window_len = (sample_rate / BELL202_BAUD).round().max(1.0) as usize;
+        let reference = |freq: f32| {
+            (0..window_len)
+                .map(|idx| Complex32::cis(-2.0 * PI * freq * idx as f32 / sample_rate))
+                .collect()
+        };
+
+        Self {
+            mark_ref: reference(BELL202_MARK),
+            space_ref: reference(BELL202_SPACE),
+            history: vec![0.0; window_len],
+            history_idx: 0,
+        }
+    }
+
+    /// Samples per bit at the given sample rate
+    pub fn samples_per_symbol(sample_rate: f32) -> f32 {
+        sample_rate / BELL202_BAUD
+    }
+
+    pub fn tick(&mut self, audio: f32) -> f32 {
+        let window_len = self.history.len();
+        self.history_idx = (self.history_idx + 1) % window_len;
+        self.history[self.history_idx] = audio;
+
+        let mut mark = Complex32::default();
+        let mut space = Complex32::default();
+        for idx in 0..window_len {
+            let sample = self.history[(self.history_idx + 1 + idx) % window_len];
+            mark += self.mark_ref[idx] * sample;
+            space += self.space_ref[idx] * sample;
+        }
+
+        let mark = mark.norm_sqr();
+        let space = space.norm_sqr();
+        (mark - space) / (mark + space + f32::EPSILON)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bell202Demodulator, BELL202_BAUD, BELL202_MARK, BELL202_SPACE};
+    use crate::hdlc::{hdlc_encode, HdlcDeframer};
+    use crate::line_coding::{LineCode, LineDecoder, LineEncoder};
+    use crate::test_utils::TestRng;
+    use crate::{GardnerErrorEstimator, SymbolSync};
+    use num::complex::Complex32;
+    use std::f32::consts::PI;
+
+    /// Phase continuous AFSK audio for the given line levels
+    fn afsk_modulate(levels: &[bool], sample_rate: f32) -> Vec<f32> {
+        let mut rng = TestRng::new(8);
+        let mut phase = 0.0f32;
+        let mut audio = Vec::new();
+        let num_samples = (levels.len() as f32 * sample_rate / BELL202_BAUD) as usize;
+        for n in 0..num_samples {
+            let level = levels[(n as f32 * BELL202_BAUD / sample_rate) as usize];
+            let freq = if level { BELL202_MARK } else { BELL202_SPACE };
+            phase += 2.0 * PI * freq / sample_rate;
+            audio.push(0.3 * phase.sin() + 0.05 * rng.gaussian());
+        }
+        audio
+    }
+
+    #[test]
+    fn test_bell202_tones() {
+        const SAMPLE_RATE: f32 = 48000.0;
+        let mut demod = Bell202Demodulator::new(SAMPLE_RATE);
+        let audio = afsk_modulate(&[true; 4], SAMPLE_RATE);
+        assert!(audio.iter().map(|val| demod.tick(*val)).last().unwrap() > 0.9);
+        let audio = afsk_modulate(&[false; 4], SAMPLE_RATE);
+        assert!(audio.iter().map(|val| demod.tick(*val)).last().unwrap() < -0.9);
+    }
+
+    #[test]
+    fn test_afsk1200_frame_decode() {
+        const SAMPLE_RATE: f32 = 22050.0;
+        let frame: Vec<u8> = b"CQ CQ DE TEST-1 AFSK1200".to_vec();
+
+        let mut nrzi = LineEncoder::new(LineCode::NrzS);
+        let levels: Vec<bool> = hdlc_encode(&frame, 16)
+            .into_iter()
+            .chain([false; 8])
+            .map(|bit| nrzi.tick(bit))
+            .collect();
+        let audio = afsk_modulate(&levels, SAMPLE_RATE);
+
+        let sps = Bell202Demodulator::samples_per_symbol(SAMPLE_RATE);
+        let mut demod = Bell202Demodulator::new(SAMPLE_RATE);
+        let mut symbol_sync = SymbolSync::new(sps, GardnerErrorEstimator {}, 0.02, 1.0);
+        let mut nrzi = LineDecoder::new(LineCode::NrzS);
+        let mut deframer = HdlcDeframer::new();
+
+        let mut frames = Vec::new();
+        for sample in audio {
+            let tone = demod.tick(sample);
+            let symbol = match symbol_sync.tick(Complex32::new(tone, 0.0)) {
+                None => continue,
+                Some(symbol) => symbol,
+            };
+            if let Some(decoded) = deframer.tick(nrzi.tick(symbol.re >= 0.0)) {
+                frames.push(decoded.to_vec());
+            }
+        }
+        assert_eq!(frames, vec![frame]);
+    }
+}
@@ -0,0 +1,140 @@
+use num::complex::Complex32;
+
+use crate::PT1;
+
+/// Default gain limit, silence between bursts is amplified by at most 20 dB
+pub const DEFAULT_MAX_GAIN_DB: f32 = 20.0;
+
+/// Output amplitude limit relative to the target, e.g. at the start of a burst
+pub const MAX_OVERSHOOT: f32 = 1.5;
+
+/// Feed-forward automatic gain control.
+///
+/// The input power is tracked by a `PT1` whose coefficient switches between
+/// `attack` (power rising) and `decay` (power falling), so a burst start is
+/// caught quickly while the gain recovers slowly in the gaps.
+/// The resulting gain is limited to `min_gain..=max_gain`.
+///
+/// A sample that would leave the AGC above `MAX_OVERSHOOT` times the target raises
+/// the power estimate at once, so the gain falls within that sample instead of
+/// following the attack, and rises again no faster than the decay allows.
+#[derive(Debug)]
+pub struct AGC {
+    power: PT1<f32>,
+    attack: f32,
+    decay: f32,
+    target: f32,
+    min_gain: f32,
+    max_gain: f32,
+    gain: f32,
+}
+
+impl AGC {
+    pub fn new(attack: f32, decay: f32, target: f32, min_gain: f32, max_gain: f32) -> Self {
+        assert!(min_gain <= max_gain);
+        Self {
+            power: PT1 {
+                alpha: attack,
+                current: target * target,
+            },
+            attack,
+            decay,
+            target,
+            min_gain,
+            max_gain,
+            gain: 1.0,
+        }
+    }
+
+    pub fn tick(&mut self, value: Complex32) -> Complex32 {
+        let value_norm_sqr = value.norm_sqr();
+        self.power.alpha = if value_norm_sqr > self.power.current {
+            self.attack
+        } else {
+            self.decay
+        };
+        let mut power = self.power.tick(value_norm_sqr);
+        let peak_power = value_norm_sqr / (MAX_OVERSHOOT * MAX_OVERSHOOT);
+        if power < peak_power {
+            power = peak_power;
+            self.power.current = peak_power;
+        }
+
+        self.gain = (self.target / power.sqrt()).clamp(self.min_gain, self.max_gain);
+        value * self.gain
+    }
+
+    /// Gain applied to the last sample
+    #[allow(dead_code)]
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    #[allow(dead_code)]
+    pub fn gain_db(&self) -> f32 {
+        20.0 * self.gain.log10()
+    }
+}
+
+/// Amplitude gain of a level change in dB
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{db_to_gain, AGC, DEFAULT_MAX_GAIN_DB, MAX_OVERSHOOT};
+    use num::complex::Complex32;
+
+    fn run(agc: &mut AGC, amplitude: f32, n: usize) -> Vec<f32> {
+        (0..n)
+            .map(|_| agc.tick(Complex32::new(amplitude, 0.0)).norm())
+            .collect()
+    }
+
+    #[test]
+    fn test_agc_step_up_converges_without_large_overshoot() {
+        let mut agc = AGC::new(0.2, 0.002, 0.5, 0.0, 1000.0);
+        run(&mut agc, 0.01, 2000);
+        let burst = run(&mut agc, 1.0, 200);
+
+        // The gain is still high at the burst start, the output is limited
+        // until the attack settles
+        let peak = burst[..20].iter().copied().fold(0.0, f32::max);
+        assert!(peak <= 0.5 * MAX_OVERSHOOT + 1e-4, "peak {}", peak);
+        assert!(burst[20..].iter().all(|out| (out - 0.5).abs() < 0.05));
+        assert!((agc.gain() - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_agc_step_down_decays_slowly() {
+        let mut agc = AGC::new(0.2, 0.002, 1.0, 0.0, 1000.0);
+        run(&mut agc, 1.0, 200);
+        let gap = run(&mut agc, 0.1, 50);
+
+        // Shortly after the burst the gap is still attenuated
+        assert!(gap.iter().all(|out| *out < 0.2));
+        let gap = run(&mut agc, 0.1, 10000);
+        assert!((gap.last().unwrap() - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_agc_gain_limits() {
+        let mut agc = AGC::new(0.1, 0.01, 1.0, 0.5, 10.0);
+        let silence = run(&mut agc, 1e-4, 5000);
+        assert_eq!(agc.gain(), 10.0);
+        assert!((agc.gain_db() - 20.0).abs() < 1e-4);
+        assert!(silence.last().unwrap() < &1e-2);
+
+        run(&mut agc, 100.0, 500);
+        assert_eq!(agc.gain(), 0.5);
+    }
+
+    #[test]
+    fn test_agc_does_not_amplify_silence_to_full_scale() {
+        let mut agc = AGC::new(0.01, 0.01, 1.0, 0.0, db_to_gain(DEFAULT_MAX_GAIN_DB));
+        let silence = run(&mut agc, 1e-4, 5000);
+        assert!((agc.gain() - db_to_gain(DEFAULT_MAX_GAIN_DB)).abs() < 1e-3);
+        assert!(silence.last().unwrap() < &0.01);
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt::Display;
+
+use crate::bytes::Bytes;
+
+const ADDRESS_LEN: usize = 7;
+const MAX_DIGIPEATERS: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ax25Address {
+    pub callsign: String,
+    pub ssid: u8,
+    /// Command/response bit for source and destination, has-been-repeated bit for digipeaters
+    pub flag: bool,
+}
+
+impl Ax25Address {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let mut callsign = String::with_capacity(6);
+        for byte in &bytes[..6] {
+            if byte & 1 != 0 {
+                return None;
+            }
+            let c = (byte >> 1) as char;
+            if !c.is_ascii_alphanumeric() && c != ' ' {
+                return None;
+            }
+            callsign.push(c);
+        }
+
+        Some(Self {
+            callsign: callsign.trim_end().to_string(),
+            ssid: (bytes[6] >> 1) & 0x0F,
+            flag: bytes[6] & 0x80 != 0,
+        })
+    }
+}
+
+impl Display for Ax25Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.callsign)?;
+        if self.ssid != 0 {
+            write!(f, "-{}", self.ssid)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ax25FrameType {
+    Information { ns: u8, nr: u8 },
+    Supervisory { nr: u8 },
+    Unnumbered,
+}
+
+/// AX.25 frame as delivered by the `HdlcDeframer`, i.e. without flags and FCS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ax25Frame<'a> {
+    pub destination: Ax25Address,
+    pub source: Ax25Address,
+    pub digipeaters: Vec<Ax25Address>,
+    pub control: u8,
+    pub pid: Option<u8>,
+    pub info: &'a [u8],
+}
+
+impl<'a> Ax25Frame<'a> {
+    pub fn parse(frame: &'a [u8]) -> Option<Self> {
+        // Address fields end with the extension bit set
+        let num_addresses = frame
+            .chunks(ADDRESS_LEN)
+            .position(|address| address.len() == ADDRESS_LEN && address[6] & 1 != 0)?
+            + 1;
+        if !(2..=2 + MAX_DIGIPEATERS).contains(&num_addresses) {
+            return None;
+        }
+
+        let mut addresses = frame[..num_addresses * ADDRESS_LEN]
+            .chunks(ADDRESS_LEN)
+            .map(Ax25Address::parse);
+        let destination = addresses.next()??;
+        let source = addresses.next()??;
+        let digipeaters = addresses.collect::<Option<Vec<_>>>()?;
+
+        let rest = &frame[num_addresses * ADDRESS_LEN..];
+        let control = *rest.first()?;
+        let frame_type = Self::frame_type_of(control);
+        // Information and UI frames carry a protocol identifier
+        let has_pid = match frame_type {
+            Ax25FrameType::Information { .. } => true,
+            Ax25FrameType::Unnumbered => control & 0xEF == 0x03,
+            Ax25FrameType::Supervisory { .. } => false,
+        };
+
+        let (pid, info) = if has_pid {
+            (Some(*rest.get(1)?), &rest[2..])
+        } else {
+            (None, &rest[1..])
+        };
+
+        Some(Self {
+            destination,
+            source,
+            digipeaters,
+            control,
+            pid,
+            info,
+        })
+    }
+
+    fn frame_type_of(control: u8) -> Ax25FrameType {
+        if control & 1 == 0 {
+            Ax25FrameType::Information {
+                ns: (control >> 1) & 7,
+                nr: control >> 5,
+            }
+        } else if control & 3 == 1 {
+            Ax25FrameType::Supervisory { nr: control >> 5 }
+        } else {
+            Ax25FrameType::Unnumbered
+        }
+    }
+
+    pub fn frame_type(&self) -> Ax25FrameType {
+        Self::frame_type_of(self.control)
+    }
+}
+
+/// TNC2 style monitor format, e.g. `N0CALL-7>APRS,WIDE1-1*: [UI pid=F0] b'...'`
+impl Display for Ax25Frame<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}>{}", self.source, self.destination)?;
+        for digipeater in self.digipeaters.iter() {
+            write!(f, ",{}", digipeater)?;
+            if digipeater.flag {
+                f.write_str("*")?;
+            }
+        }
+
+        match self.frame_type() {
+            Ax25FrameType::Information { ns, nr } => write!(f, ": [I ns={} nr={}", ns, nr)?,
+            Ax25FrameType::Supervisory { nr } => write!(f, ": [S {:#04x} nr={}", self.control, nr)?,
+            Ax25FrameType::Unnumbered if self.pid.is_some() => f.write_str(": [UI")?,
+            Ax25FrameType::Unnumbered => write!(f, ": [U {:#04x}", self.control)?,
+        }
+        if let Some(pid) = self.pid {
+            write!(f, " pid={:02X}", pid)?;
+        }
+        write!(f, "] {}", Bytes(self.info))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ax25Frame, Ax25FrameType};
+
+    fn address(callsign: &str, ssid: u8, last: bool) -> Vec<u8> {
+        let mut bytes: Vec<u8> = format!("{:<6}", callsign).bytes().map(|c| c << 1).collect();
+        bytes.push(0x60 | (ssid << 1) | last as u8);
+        bytes
+    }
+
+    #[test]
+    fn test_ax25_ui_frame() {
+        let mut frame = address("APRS", 0, false);
+        frame.extend(address("N0CALL", 7, false));
+        let mut digipeater = address("WIDE1", 1, true);
+        digipeater[6] |= 0x80;
+        frame.extend(digipeater);
+        frame.extend([0x03, 0xF0]);
+        frame.extend(b"!4903.50N/07201.75W-Test");
+
+        let parsed = Ax25Frame::parse(&frame).unwrap();
+        assert_eq!(parsed.source.callsign, "N0CALL");
+        assert_eq!(parsed.source.ssid, 7);
+        assert_eq!(parsed.digipeaters.len(), 1);
+        assert_eq!(parsed.frame_type(), Ax25FrameType::Unnumbered);
+        assert_eq!(parsed.pid, Some(0xF0));
+        assert_eq!(parsed.info, b"!4903.50N/07201.75W-Test");
+        assert_eq!(
+            parsed.to_string(),
+            "N0CALL-7>APRS,WIDE1-1*: [UI pid=F0] b'!4903.50N/07201.75W-Test'"
+        );
+    }
+
+    #[test]
+    fn test_ax25_supervisory_frame_and_invalid() {
+        let mut frame = address("DEST", 2, false);
+        frame.extend(address("SRC", 0, true));
+        frame.push(0x41);
+        let parsed = Ax25Frame::parse(&frame).unwrap();
+        assert_eq!(parsed.frame_type(), Ax25FrameType::Supervisory { nr: 2 });
+        assert_eq!(parsed.pid, None);
+        assert!(parsed.info.is_empty());
+
+        // No end of address marker
+        assert_eq!(Ax25Frame::parse(&frame[..7]), None);
+        // Missing control field
+        assert_eq!(Ax25Frame::parse(&frame[..14]), None);
+    }
+}
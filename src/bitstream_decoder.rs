@@ -1,4 +1,7 @@
+use crate::soft_bit::SoftBit;
+
 pub enum BitOrder {
+    #[allow(dead_code)]
     LittleEndian,
     BigEndian,
 }
@@ -8,6 +11,7 @@ pub struct BitStreamDecoder {
 }
 
 impl BitStreamDecoder {
+    #[allow(dead_code)]
     pub const LE: Self = Self {
         bit_order: BitOrder::LittleEndian,
     };
@@ -16,7 +20,8 @@ impl BitStreamDecoder {
         bit_order: BitOrder::BigEndian,
     };
 
-    pub fn decode(&self, bitstream: &[bool]) -> Vec<u8> {
+    /// Packs the (hard decided) bits into bytes
+    pub fn decode<S: SoftBit>(&self, bitstream: &[S]) -> Vec<u8> {
         assert!(bitstream.len() & 7 == 0);
         let mut bitstream_iter = bitstream.iter();
         let mut bytestream = Vec::with_capacity(bitstream.len() / 8);
@@ -29,38 +34,38 @@ impl BitStreamDecoder {
 
             match self.bit_order {
                 BitOrder::BigEndian => {
-                    acc |= *bit as u8;
+                    acc |= bit.hard() as u8;
                     acc <<= 1;
-                    acc |= *bitstream_iter.next().unwrap() as u8;
+                    acc |= bitstream_iter.next().unwrap().hard() as u8;
                     acc <<= 1;
-                    acc |= *bitstream_iter.next().unwrap() as u8;
+                    acc |= bitstream_iter.next().unwrap().hard() as u8;
                     acc <<= 1;
-                    acc |= *bitstream_iter.next().unwrap() as u8;
+                    acc |= bitstream_iter.next().unwrap().hard() as u8;
                     acc <<= 1;
-                    acc |= *bitstream_iter.next().unwrap() as u8;
+                    acc |= bitstream_iter.next().unwrap().hard() as u8;
                     acc <<= 1;
-                    acc |= *bitstream_iter.next().unwrap() as u8;
+                    acc |= bitstream_iter.next().unwrap().hard() as u8;
                     acc <<= 1;
-                    acc |= *bitstream_iter.next().unwrap() as u8;
+                    acc |= bitstream_iter.next().unwrap().hard() as u8;
                     acc <<= 1;
-                    acc |= *bitstream_iter.next().unwrap() as u8;
+                    acc |= bitstream_iter.next().unwrap().hard() as u8;
                 }
                 BitOrder::LittleEndian => {
-                    acc |= *bit as u8;
+                    acc |= bit.hard() as u8;
                     acc = acc.rotate_right(1);
-                    acc |= *bitstream_iter.next().unwrap() as u8;
+                    acc |= bitstream_iter.next().unwrap().hard() as u8;
                     acc = acc.rotate_right(1);
-                    acc |= *bitstream_iter.next().unwrap() as u8;
+                    acc |= bitstream_iter.next().unwrap().hard() as u8;
                     acc = acc.rotate_right(1);
-                    acc |= *bitstream_iter.next().unwrap() as u8;
+                    acc |= bitstream_iter.next().unwrap().hard() as u8;
                     acc = acc.rotate_right(1);
-                    acc |= *bitstream_iter.next().unwrap() as u8;
+                    acc |= bitstream_iter.next().unwrap().hard() as u8;
                     acc = acc.rotate_right(1);
-                    acc |= *bitstream_iter.next().unwrap() as u8;
+                    acc |= bitstream_iter.next().unwrap().hard() as u8;
                     acc = acc.rotate_right(1);
-                    acc |= *bitstream_iter.next().unwrap() as u8;
+                    acc |= bitstream_iter.next().unwrap().hard() as u8;
                     acc = acc.rotate_right(1);
-                    acc |= *bitstream_iter.next().unwrap() as u8;
+                    acc |= bitstream_iter.next().unwrap().hard() as u8;
                     acc = acc.rotate_right(1);
                 }
             }
@@ -88,5 +93,8 @@ mod tests {
         let input = &[false, false, false, false, true, true, true, true];
         assert_eq!(decoder_le.decode(input)[0], 0xF0u8);
         assert_eq!(decoder_be.decode(input)[0], 0x0Fu8);
+
+        let soft_input = &[-0.5f32, -2.0, -0.1, -3.0, 0.2, 1.0, 4.0, 0.0];
+        assert_eq!(decoder_be.decode(soft_input)[0], 0x0Fu8);
     }
 }
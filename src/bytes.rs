@@ -1,4 +1,4 @@
-use std::{fmt::Write, slice::EscapeAscii};
+use std::fmt::Write;
 
 pub struct Bytes<T>(pub T)
 where
@@ -0,0 +1,151 @@
+//! CCSDS TM synchronization and channel coding (CCSDS 131.0-B)
+use std::fmt::Display;
+
+use crate::fec::reed_solomon::RsCorrections;
+
+pub mod tm;
+
+/// Attached sync marker
+pub const ASM: u64 = 0x1ACFFC1D;
+/// Interleave depth of the RS(255,223) coded 1275 byte frames
+pub const RS_INTERLEAVE_DEPTH: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameQuality {
+    /// All codewords were error free
+    Clean,
+    /// Symbols corrected in all codewords
+    Corrected(usize),
+    /// At least one codeword could not be corrected
+    Uncorrectable,
+}
+
+impl FrameQuality {
+    pub fn from_rs(results: &[Option<RsCorrections>]) -> Self {
+        let corrected: Option<usize> = results
+            .iter()
+            .map(|result| result.map(|corrections| corrections.total()))
+            .sum();
+        match corrected {
+            None => Self::Uncorrectable,
+            Some(0) => Self::Clean,
+            Some(corrected) => Self::Corrected(corrected),
+        }
+    }
+}
+
+impl Display for FrameQuality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Clean => write!(f, "clean"),
+            Self::Corrected(symbols) => write!(f, "corrected ({} symbols)", symbols),
+            Self::Uncorrectable => write!(f, "uncorrectable"),
+        }
+    }
+}
+
+/// Frame counts by quality over a whole run
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    pub clean: usize,
+    pub corrected: usize,
+    pub uncorrectable: usize,
+    pub corrected_symbols: usize,
+}
+
+impl FrameStats {
+    pub fn record(&mut self, quality: FrameQuality) {
+        match quality {
+            FrameQuality::Clean => self.clean += 1,
+            FrameQuality::Corrected(symbols) => {
+                self.corrected += 1;
+                self.corrected_symbols += symbols;
+            }
+            FrameQuality::Uncorrectable => self.uncorrectable += 1,
+        }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.clean + self.corrected + self.uncorrectable
+    }
+}
+
+impl Display for FrameStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} frames: {} clean, {} corrected ({} symbols), {} uncorrectable",
+            self.frames(),
+            self.clean,
+            self.corrected,
+            self.corrected_symbols,
+            self.uncorrectable
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FrameQuality, FrameStats, ASM, RS_INTERLEAVE_DEPTH};
+    use crate::bitstream_decoder::BitStreamDecoder;
+    use crate::fec::reed_solomon::ReedSolomon;
+    use crate::fec::viterbi::{
+        ConvolutionalCode, ConvolutionalDecoder, ConvolutionalEncoder, Puncturing,
+    };
+    use crate::signals::lsfr::LSFR;
+    use crate::syncword::{syncword_bits, PackedSyncwordScan, PhaseAmbiguity, SyncwordPacketizer};
+    use crate::test_utils::{bytes_to_bits, TestRng};
+
+    /// Transfer frame -> RS -> randomizer -> ASM -> convolutional code -> noisy BPSK,
+    /// and back through the decoding chain of the `ccsds` preset
+    #[test]
+    fn test_concatenated_coding_chain() {
+        const FRAME_LEN: usize = 1275;
+        let rs = ReedSolomon::ccsds();
+        let code = ConvolutionalCode::ccsds();
+        let mut rng = TestRng::new(44);
+
+        let mut frames = Vec::new();
+        let mut bits: Vec<bool> = (0..100).map(|_| rng.next_bool()).collect();
+        for _ in 0..3 {
+            let mut frame: Vec<u8> = (0..FRAME_LEN).map(|_| rng.next_u64() as u8).collect();
+            rs.encode_interleaved(&mut frame, RS_INTERLEAVE_DEPTH);
+            frames.push(frame.clone());
+
+            let mut frame_bits = bytes_to_bits(&frame);
+            LSFR::<u8>::new(0b10101001, 0xFF).derandomize(&mut frame_bits);
+            bits.extend(syncword_bits::<32>(ASM));
+            bits.extend(frame_bits);
+        }
+        bits.extend((0..100).map(|_| rng.next_bool()));
+
+        let mut encoder = ConvolutionalEncoder::new(code.clone(), Puncturing::none());
+        let mut coded = Vec::new();
+        for bit in bits {
+            encoder.tick(bit, &mut coded);
+        }
+        // Inverted BPSK with a raw bit error rate of about 5%
+        let received = coded
+            .iter()
+            .map(|bit| if *bit { -1.0 } else { 1.0 } + 0.6 * rng.gaussian());
+
+        let mut viterbi = ConvolutionalDecoder::new(&code, &Puncturing::none(), false);
+        let detector = PackedSyncwordScan::new(ASM, 32, 1, PhaseAmbiguity::Bpsk);
+        let mut packetizer = SyncwordPacketizer::new(detector, FRAME_LEN * 8);
+        let mut stats = FrameStats::default();
+        for llr in received {
+            let packet = match viterbi.tick(llr).and_then(|bit| packetizer.tick(bit)) {
+                None => continue,
+                Some(packet) => packet,
+            };
+            LSFR::<u8>::new(0b10101001, 0xFF).derandomize(packet);
+            let mut frame = BitStreamDecoder::BE.decode(packet);
+            let quality =
+                FrameQuality::from_rs(&rs.decode_interleaved(&mut frame, RS_INTERLEAVE_DEPTH, &[]));
+            assert_ne!(quality, FrameQuality::Uncorrectable);
+            assert_eq!(frame, frames[stats.frames()]);
+            stats.record(quality);
+        }
+        assert_eq!(stats.frames(), frames.len());
+    }
+}
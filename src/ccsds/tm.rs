@@ -0,0 +1,400 @@
+//! TM space data link protocol transfer frames (CCSDS 132.0-B)
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use crate::crc::Crc;
+
+pub const PRIMARY_HEADER_LEN: usize = 6;
+const OCF_LEN: usize = 4;
+const FECF_LEN: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TmPrimaryHeader {
+    pub version: u8,
+    pub spacecraft_id: u16,
+    pub virtual_channel: u8,
+    pub ocf_present: bool,
+    pub mc_frame_count: u8,
+    pub vc_frame_count: u8,
+    // Data field status
+    pub secondary_header: bool,
+    pub sync: bool,
+    pub packet_order: bool,
+    pub segment_length_id: u8,
+    pub first_header_pointer: u16,
+}
+
+impl TmPrimaryHeader {
+    /// First header pointer of a data field without packet start
+    pub const NO_PACKET_START: u16 = 0x7FF;
+    /// First header pointer of a data field with idle data only
+    pub const IDLE: u16 = 0x7FE;
+
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let bytes = bytes.get(..PRIMARY_HEADER_LEN)?;
+        let id = u16::from_be_bytes([bytes[0], bytes[1]]);
+        let status = u16::from_be_bytes([bytes[4], bytes[5]]);
+        Some(Self {
+            version: (id >> 14) as u8,
+            spacecraft_id: (id >> 4) & 0x3FF,
+            virtual_channel: ((id >> 1) & 0x7) as u8,
+            ocf_present: id & 1 != 0,
+            mc_frame_count: bytes[2],
+            vc_frame_count: bytes[3],
+            secondary_header: status & 0x8000 != 0,
+            sync: status & 0x4000 != 0,
+            packet_order: status & 0x2000 != 0,
+            segment_length_id: ((status >> 11) & 0x3) as u8,
+            first_header_pointer: status & 0x7FF,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn to_bytes(self) -> [u8; PRIMARY_HEADER_LEN] {
+        let id = ((self.version as u16 & 0x3) << 14)
+            | ((self.spacecraft_id & 0x3FF) << 4)
+            | ((self.virtual_channel as u16 & 0x7) << 1)
+            | self.ocf_present as u16;
+        let status = ((self.secondary_header as u16) << 15)
+            | ((self.sync as u16) << 14)
+            | ((self.packet_order as u16) << 13)
+            | ((self.segment_length_id as u16 & 0x3) << 11)
+            | (self.first_header_pointer & 0x7FF);
+        let [id_hi, id_lo] = id.to_be_bytes();
+        let [status_hi, status_lo] = status.to_be_bytes();
+        [
+            id_hi,
+            id_lo,
+            self.mc_frame_count,
+            self.vc_frame_count,
+            status_hi,
+            status_lo,
+        ]
+    }
+}
+
+impl Display for TmPrimaryHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "version {}, SCID {:#05x}, VC {}, MC count {}, VC count {}, ",
+            self.version,
+            self.spacecraft_id,
+            self.virtual_channel,
+            self.mc_frame_count,
+            self.vc_frame_count
+        )?;
+        match self.first_header_pointer {
+            Self::NO_PACKET_START => write!(f, "no packet start")?,
+            Self::IDLE => write!(f, "idle")?,
+            pointer => write!(f, "first header @ {}", pointer)?,
+        }
+        if self.sync {
+            write!(f, ", sync")?;
+        }
+        Ok(())
+    }
+}
+
+/// Communications link control word (CCSDS 232.0-B), the type-1 report in the OCF
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clcw {
+    pub version: u8,
+    pub status: u8,
+    pub cop_in_effect: u8,
+    pub virtual_channel: u8,
+    pub no_rf_available: bool,
+    pub no_bit_lock: bool,
+    pub lockout: bool,
+    pub wait: bool,
+    pub retransmit: bool,
+    pub farm_b_counter: u8,
+    /// Next expected frame sequence number N(R)
+    pub report_value: u8,
+}
+
+impl Clcw {
+    fn from_word(word: u32) -> Self {
+        Self {
+            version: ((word >> 29) & 0x3) as u8,
+            status: ((word >> 26) & 0x7) as u8,
+            cop_in_effect: ((word >> 24) & 0x3) as u8,
+            virtual_channel: ((word >> 18) & 0x3F) as u8,
+            no_rf_available: word & (1 << 15) != 0,
+            no_bit_lock: word & (1 << 14) != 0,
+            lockout: word & (1 << 13) != 0,
+            wait: word & (1 << 12) != 0,
+            retransmit: word & (1 << 11) != 0,
+            farm_b_counter: ((word >> 9) & 0x3) as u8,
+            report_value: word as u8,
+        }
+    }
+}
+
+impl Display for Clcw {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "VC {}, report value {}, FARM-B count {}",
+            self.virtual_channel, self.report_value, self.farm_b_counter
+        )?;
+        for (flag, name) in [
+            (self.no_rf_available, "no RF"),
+            (self.no_bit_lock, "no bit lock"),
+            (self.lockout, "lockout"),
+            (self.wait, "wait"),
+            (self.retransmit, "retransmit"),
+        ] {
+            if flag {
+                write!(f, ", {}", name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Operational control field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ocf {
+    Clcw(Clcw),
+    /// Type-2 report, mission specific
+    Other(u32),
+}
+
+impl Ocf {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let word = u32::from_be_bytes(bytes.try_into().unwrap());
+        if word & (1 << 31) == 0 {
+            Self::Clcw(Clcw::from_word(word))
+        } else {
+            Self::Other(word)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TmFrame<'a> {
+    pub header: TmPrimaryHeader,
+    /// Secondary header including its identification byte
+    pub secondary_header: Option<&'a [u8]>,
+    pub data: &'a [u8],
+    pub ocf: Option<Ocf>,
+    /// Whether the frame error control field matches, if the frame has one
+    pub fecf_valid: Option<bool>,
+}
+
+impl<'a> TmFrame<'a> {
+    /// Splits a frame into its fields, the presence of the FECF is managed per
+    /// mission and not signalled in the frame
+    pub fn parse(frame: &'a [u8], fecf: bool) -> Option<Self> {
+        let header = TmPrimaryHeader::parse(frame)?;
+        let mut end = frame.len();
+        let fecf_valid = if fecf {
+            end = end.checked_sub(FECF_LEN)?;
+            Some(Crc::ccitt().check(frame))
+        } else {
+            None
+        };
+        let ocf = if header.ocf_present {
+            end = end.checked_sub(OCF_LEN)?;
+            Some(Ocf::from_bytes(&frame[end..end + OCF_LEN]))
+        } else {
+            None
+        };
+
+        let mut start = PRIMARY_HEADER_LEN;
+        let secondary_header = if header.secondary_header {
+            let len = (*frame.get(start)? & 0x3F) as usize + 1;
+            let secondary_header = frame.get(start..start + len)?;
+            start += len;
+            Some(secondary_header)
+        } else {
+            None
+        };
+        Some(Self {
+            header,
+            secondary_header,
+            data: frame.get(start..end)?,
+            ocf,
+            fecf_valid,
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct ChannelState {
+    last_count: Option<u8>,
+    frames: usize,
+    missing: usize,
+}
+
+impl ChannelState {
+    /// Records a frame count and returns the number of frames skipped since the last one
+    fn record(&mut self, count: u8) -> usize {
+        let missing = match self.last_count {
+            Some(last) => count.wrapping_sub(last).wrapping_sub(1) as usize,
+            None => 0,
+        };
+        self.last_count = Some(count);
+        self.frames += 1;
+        self.missing += missing;
+        missing
+    }
+}
+
+/// Frames missing before the current one according to the modulo 256 frame counts
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FrameGaps {
+    pub master_channel: usize,
+    pub virtual_channel: usize,
+}
+
+/// Tracks the frame counts per master channel (version and SCID) and per virtual channel
+#[derive(Debug, Default)]
+pub struct TmDemultiplexer {
+    master_channels: BTreeMap<(u8, u16), ChannelState>,
+    virtual_channels: BTreeMap<(u8, u16, u8), ChannelState>,
+}
+
+impl TmDemultiplexer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, header: &TmPrimaryHeader) -> FrameGaps {
+        let mc_id = (header.version, header.spacecraft_id);
+        let vc_id = (header.version, header.spacecraft_id, header.virtual_channel);
+        FrameGaps {
+            master_channel: self
+                .master_channels
+                .entry(mc_id)
+                .or_default()
+                .record(header.mc_frame_count),
+            virtual_channel: self
+                .virtual_channels
+                .entry(vc_id)
+                .or_default()
+                .record(header.vc_frame_count),
+        }
+    }
+
+    /// Frames received and missed on a virtual channel
+    #[cfg(test)]
+    pub fn virtual_channel(&self, spacecraft_id: u16, vcid: u8) -> Option<(usize, usize)> {
+        self.virtual_channels
+            .iter()
+            .find(|((_, scid, vc), _)| *scid == spacecraft_id && *vc == vcid)
+            .map(|(_, state)| (state.frames, state.missing))
+    }
+}
+
+impl Display for TmDemultiplexer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for ((version, scid, vcid), state) in self.virtual_channels.iter() {
+            writeln!(
+                f,
+                "TM version {} SCID {:#05x} VC {}: {} frames, {} missing",
+                version, scid, vcid, state.frames, state.missing
+            )?;
+        }
+        for ((version, scid), state) in self.master_channels.iter() {
+            writeln!(
+                f,
+                "TM version {} SCID {:#05x}: {} frames, {} missing",
+                version, scid, state.frames, state.missing
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FrameGaps, Ocf, TmDemultiplexer, TmFrame, TmPrimaryHeader};
+    use crate::crc::Crc;
+
+    fn header(vc: u8, mc_count: u8, vc_count: u8) -> TmPrimaryHeader {
+        TmPrimaryHeader {
+            version: 0,
+            spacecraft_id: 0x1AB,
+            virtual_channel: vc,
+            ocf_present: true,
+            mc_frame_count: mc_count,
+            vc_frame_count: vc_count,
+            secondary_header: false,
+            sync: false,
+            packet_order: false,
+            segment_length_id: 3,
+            first_header_pointer: 0,
+        }
+    }
+
+    #[test]
+    fn test_tm_frame_fields() {
+        let header = TmPrimaryHeader {
+            secondary_header: true,
+            first_header_pointer: TmPrimaryHeader::NO_PACKET_START,
+            ..header(5, 200, 17)
+        };
+        let mut frame = header.to_bytes().to_vec();
+        assert_eq!(frame, [0x1A, 0xBB, 200, 17, 0x9F, 0xFF]);
+        assert_eq!(TmPrimaryHeader::parse(&frame), Some(header));
+
+        // Secondary header of 3 bytes, data, CLCW for VC 5 with N(R) = 42, lockout and wait
+        frame.extend([0x02, 0xAA, 0xBB]);
+        frame.extend(b"data");
+        frame.extend([0x01, 0x14, 0x30, 42]);
+        let crc = Crc::ccitt();
+        frame.extend(crc.to_bytes(crc.checksum(&frame)));
+
+        let parsed = TmFrame::parse(&frame, true).unwrap();
+        assert_eq!(parsed.secondary_header, Some(&[0x02, 0xAA, 0xBB][..]));
+        assert_eq!(parsed.data, b"data");
+        assert_eq!(parsed.fecf_valid, Some(true));
+        let Some(Ocf::Clcw(clcw)) = parsed.ocf else {
+            panic!("no CLCW");
+        };
+        assert_eq!(clcw.cop_in_effect, 1);
+        assert_eq!(clcw.virtual_channel, 5);
+        assert_eq!(clcw.report_value, 42);
+        assert!(clcw.lockout && clcw.wait && !clcw.retransmit && !clcw.no_rf_available);
+        assert_eq!(
+            clcw.to_string(),
+            "VC 5, report value 42, FARM-B count 0, lockout, wait"
+        );
+
+        let ocf = parsed.ocf;
+        frame[8] ^= 1;
+        assert_eq!(
+            TmFrame::parse(&frame, true).unwrap().fecf_valid,
+            Some(false)
+        );
+        // Without FECF the last two bytes are taken as OCF
+        assert_ne!(TmFrame::parse(&frame, false).unwrap().ocf, ocf);
+        assert_eq!(TmFrame::parse(&frame[..10], true), None);
+    }
+
+    #[test]
+    fn test_frame_count_gaps() {
+        let mut demux = TmDemultiplexer::new();
+        let gaps: Vec<FrameGaps> = [
+            header(0, 254, 10),
+            header(1, 255, 0),
+            // MC frame 0 lost on VC 0
+            header(0, 1, 12),
+            header(1, 2, 1),
+            header(1, 3, 2),
+        ]
+        .iter()
+        .map(|header| demux.record(header))
+        .collect();
+        let missing: Vec<(usize, usize)> = gaps
+            .iter()
+            .map(|gaps| (gaps.master_channel, gaps.virtual_channel))
+            .collect();
+        assert_eq!(missing, [(0, 0), (0, 0), (1, 1), (0, 0), (0, 0)]);
+        assert_eq!(demux.virtual_channel(0x1AB, 0), Some((2, 1)));
+        assert_eq!(demux.virtual_channel(0x1AB, 1), Some((3, 0)));
+        assert_eq!(demux.virtual_channel(0x1AB, 2), None);
+    }
+}
@@ -18,6 +18,7 @@ impl Default for SimpleClock {
 }
 
 impl SimpleClock {
+    #[allow(dead_code)]
     pub fn new(phase: f32, rate: f32) -> Self {
         Self { phase, rate }
     }
@@ -48,21 +49,29 @@ impl SimpleClock {
         None
     }
 
+    #[allow(dead_code)]
     pub fn phase(&self) -> f32 {
         self.phase
     }
 
+    pub fn set_phase(&mut self, phase: f32) {
+        self.phase = phase;
+    }
+
     #[inline]
+    #[allow(dead_code)]
     pub fn sin(&self) -> f32 {
         self.phase.sin()
     }
 
     #[inline]
+    #[allow(dead_code)]
     pub fn cos(&self) -> f32 {
         self.phase.cos()
     }
 
     #[inline]
+    #[allow(dead_code)]
     pub fn cis(&self) -> Complex32 {
         Complex32::cis(self.phase)
     }
@@ -0,0 +1,342 @@
+use std::f32::consts::PI;
+
+use num::complex::Complex32;
+
+/// Symbol constellation with a hard-decision demapper.
+///
+/// `points[idx]` is transmitted for the bit pattern `bit_map[idx]`,
+/// bits are emitted MSB first. Positive I (and Q) map to `true` bits
+/// for BPSK and QPSK, matching the `re >= 0.0` slicer.
+#[derive(Debug, Clone)]
+pub struct Constellation {
+    points: Vec<Complex32>,
+    bit_map: Vec<u8>,
+    bits_per_symbol: u32,
+}
+
+impl Constellation {
+    pub fn new(points: Vec<Complex32>, bit_map: Vec<u8>) -> Self {
+        assert!(points.len().is_power_of_two() && points.len() > 1);
+        assert_eq!(points.len(), bit_map.len());
+        let mut seen = vec![false; bit_map.len()];
+        for bits in bit_map.iter() {
+            assert!(!seen[*bits as usize], "bit map is no permutation");
+            seen[*bits as usize] = true;
+        }
+
+        Self {
+            bits_per_symbol: points.len().trailing_zeros(),
+            points,
+            bit_map,
+        }
+    }
+
+    pub fn bpsk() -> Self {
+        Self::new(
+            vec![Complex32::new(-1.0, 0.0), Complex32::new(1.0, 0.0)],
+            vec![0b0, 0b1],
+        )
+    }
+
+    /// Gray coded QPSK, the first bit is taken from I, the second from Q
+    pub fn qpsk() -> Self {
+        Self::new(psk_points(4, PI / 4.0), vec![0b11, 0b01, 0b00, 0b10])
+    }
+
+    /// Gray coded 8PSK, point 0 at 0°
+    pub fn psk8() -> Self {
+        Self::new(psk_points(8, 0.0), gray_code(3))
+    }
+
+    /// Replaces the bit map, e.g. for natural (non Gray) coding or mission specific maps
+    #[cfg(test)]
+    pub fn with_bit_map(self, bit_map: Vec<u8>) -> Self {
+        Self::new(self.points, bit_map)
+    }
+
+    #[cfg(test)]
+    pub fn bits_per_symbol(&self) -> u32 {
+        self.bits_per_symbol
+    }
+
+    /// Squared distance of the closest pair of points
+    pub fn min_distance_sqr(&self) -> f32 {
+        let mut min = f32::INFINITY;
+        for (idx, a) in self.points.iter().enumerate() {
+            for b in &self.points[idx + 1..] {
+                min = min.min((a - b).norm_sqr());
+            }
+        }
+        min
+    }
+
+    pub fn points(&self) -> &[Complex32] {
+        &self.points
+    }
+
+    /// Index of the constellation point closest to `sample`
+    pub fn nearest(&self, sample: Complex32) -> usize {
+        let mut best_idx = 0;
+        let mut best_dist = f32::INFINITY;
+        for (idx, point) in self.points.iter().enumerate() {
+            let dist = (sample - point).norm_sqr();
+            if dist < best_dist {
+                best_dist = dist;
+                best_idx = idx;
+            }
+        }
+        best_idx
+    }
+
+    /// Hard decision, returns the bit pattern of the closest point
+    pub fn decide(&self, sample: Complex32) -> u8 {
+        self.bit_map[self.nearest(sample)]
+    }
+
+    /// Maps a bit pattern to its constellation point
+    pub fn map(&self, bits: u8) -> Complex32 {
+        let idx = self
+            .bit_map
+            .iter()
+            .position(|val| *val == bits)
+            .expect("bit pattern out of range");
+        self.points[idx]
+    }
+
+    /// Max-log LLR of bit `bit_idx` (0 being the MSB), for a complex
+    /// noise variance of `noise_var`. Positive values indicate a `true` bit.
+    pub fn bit_llr(&self, sample: Complex32, noise_var: f32, bit_idx: u32) -> f32 {
+        let shift = self.bits_per_symbol - 1 - bit_idx;
+        let mut min_dist = [f32::INFINITY; 2];
+        for (point, bits) in self.points.iter().zip(self.bit_map.iter()) {
+            let bit = ((bits >> shift) & 1) as usize;
+            min_dist[bit] = min_dist[bit].min((sample - point).norm_sqr());
+        }
+        (min_dist[0] - min_dist[1]) / noise_var
+    }
+
+    /// Soft decision, iterates the LLRs of all bits of `sample`, MSB first
+    pub fn soft_bits(&self, sample: Complex32, noise_var: f32) -> impl Iterator<Item = f32> + '_ {
+        (0..self.bits_per_symbol).map(move |bit_idx| self.bit_llr(sample, noise_var, bit_idx))
+    }
+
+    /// Iterates the bits of a decided symbol, MSB first
+    pub fn symbol_bits(&self, symbol: u8) -> impl Iterator<Item = bool> {
+        (0..self.bits_per_symbol)
+            .rev()
+            .map(move |bit_idx| (symbol >> bit_idx) & 1 != 0)
+    }
+}
+
+fn psk_points(n: usize, offset: f32) -> Vec<Complex32> {
+    (0..n)
+        .map(|idx| Complex32::cis(offset + 2.0 * PI * idx as f32 / n as f32))
+        .collect()
+}
+
+/// Binary reflected Gray code for `bits` wide symbols
+pub fn gray_code(bits: u32) -> Vec<u8> {
+    (0..1u16 << bits)
+        .map(|idx| (idx ^ (idx >> 1)) as u8)
+        .collect()
+}
+
+/// Delays I by half a symbol, so OQPSK (Q lagging I by half a symbol)
+/// can be timing recovered and sliced like QPSK.
+#[derive(Debug)]
+pub struct OQPSKAligner {
+    delay_line: Vec<f32>,
+    write_idx: usize,
+    delay_int: usize,
+    delay_frac: f32,
+}
+
+impl OQPSKAligner {
+    pub fn new(sps: f32) -> Self {
+        let delay = sps / 2.0;
+        let delay_int = delay.floor() as usize;
+        Self {
+            delay_line: vec![0.0; delay_int + 2],
+            write_idx: 0,
+            delay_int,
+            delay_frac: delay - delay.floor(),
+        }
+    }
+
+    pub fn tick(&mut self, sample: Complex32) -> Complex32 {
+        let n = self.delay_line.len();
+        self.delay_line[self.write_idx] = sample.re;
+
+        // Linear interpolation between the two samples around the fractional delay
+        let a = self.delay_line[(self.write_idx + n - self.delay_int) % n];
+        let b = self.delay_line[(self.write_idx + n - self.delay_int - 1) % n];
+        self.write_idx = (self.write_idx + 1) % n;
+
+        let re = a * (1.0 - self.delay_frac) + b * self.delay_frac;
+        Complex32::new(re, sample.im)
+    }
+}
+
+/// Differentially coherent detector for D-PSK: rotates each symbol back by the
+/// phase of its predecessor, so the output can be sliced with the non
+/// differential constellation. An unchanged phase decodes as the all zero symbol,
+/// which makes the decisions independent of the carrier phase ambiguity.
+#[derive(Debug)]
+pub struct DifferentialDetector {
+    reference: Complex32,
+    last: Complex32,
+}
+
+impl DifferentialDetector {
+    pub fn new(constellation: &Constellation) -> Self {
+        let zero = constellation.map(0);
+        Self {
+            reference: zero / zero.norm(),
+            last: Complex32::new(1.0, 0.0),
+        }
+    }
+
+    pub fn tick(&mut self, symbol: Complex32) -> Complex32 {
+        let last_norm = self.last.norm().max(f32::EPSILON);
+        let diff = symbol * self.last.conj() / last_norm;
+        self.last = symbol;
+        diff * self.reference
+    }
+}
+
+/// Matching encoder, advances the transmitted phase by the symbol's phase
+/// relative to the all zero symbol
+#[cfg(test)]
+#[derive(Debug)]
+pub struct DifferentialEncoder {
+    rotations: Vec<Complex32>,
+    current: Complex32,
+}
+
+#[cfg(test)]
+impl DifferentialEncoder {
+    pub fn new(constellation: &Constellation) -> Self {
+        let zero = constellation.map(0);
+        let rotations = (0..1u8 << constellation.bits_per_symbol())
+            .map(|bits| constellation.map(bits) / zero)
+            .collect();
+        Self {
+            rotations,
+            current: zero,
+        }
+    }
+
+    pub fn tick(&mut self, bits: u8) -> Complex32 {
+        self.current *= self.rotations[bits as usize];
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        gray_code, Constellation, DifferentialDetector, DifferentialEncoder, OQPSKAligner,
+    };
+    use num::complex::Complex32;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_constellation_roundtrip() {
+        let noise = Complex32::new(0.1, -0.08);
+        for constellation in [
+            Constellation::bpsk(),
+            Constellation::qpsk(),
+            Constellation::psk8(),
+            Constellation::psk8().with_bit_map((0..8).collect()),
+        ] {
+            for bits in 0..(1u8 << constellation.bits_per_symbol()) {
+                let sample = constellation.map(bits) * 0.7 + noise;
+                assert_eq!(constellation.decide(sample), bits);
+            }
+        }
+    }
+
+    #[test]
+    fn test_constellation_gray_neighbours() {
+        for constellation in [Constellation::qpsk(), Constellation::psk8()] {
+            let n = constellation.points().len();
+            for idx in 0..n {
+                let a = constellation.decide(constellation.points()[idx]);
+                let b = constellation.decide(constellation.points()[(idx + 1) % n]);
+                assert_eq!((a ^ b).count_ones(), 1);
+            }
+        }
+        assert_eq!(gray_code(2), vec![0, 1, 3, 2]);
+    }
+
+    #[test]
+    fn test_qpsk_matches_bpsk_slicer() {
+        let qpsk = Constellation::qpsk();
+        let sample = Complex32::new(0.8, -0.6);
+        let bits: Vec<bool> = qpsk.symbol_bits(qpsk.decide(sample)).collect();
+        assert_eq!(bits, vec![sample.re >= 0.0, sample.im >= 0.0]);
+    }
+
+    #[test]
+    fn test_soft_bits() {
+        let bpsk = Constellation::bpsk();
+        let llr: Vec<f32> = bpsk.soft_bits(Complex32::new(0.25, 0.1), 0.5).collect();
+        assert_eq!(llr, vec![4.0 * 0.25 / 0.5]);
+
+        let psk8 = Constellation::psk8();
+        let sample = psk8.map(0b110) * 0.9 + Complex32::new(0.05, 0.1);
+        let hard: Vec<bool> = psk8.symbol_bits(psk8.decide(sample)).collect();
+        let soft: Vec<bool> = psk8.soft_bits(sample, 0.1).map(|llr| llr >= 0.0).collect();
+        assert_eq!(hard, soft);
+    }
+
+    #[test]
+    fn test_oqpsk_alignment() {
+        const SPS: usize = 8;
+        let symbols = [
+            (1.0, -1.0),
+            (-1.0, -1.0),
+            (1.0, 1.0),
+            (-1.0, 1.0),
+            (1.0, -1.0),
+        ];
+
+        // Rectangular pulses, Q lagging I by half a symbol
+        let mut samples = Vec::new();
+        for n in 0..(symbols.len() * SPS) {
+            let i = symbols[n / SPS].0;
+            let q = match (n + SPS - SPS / 2) / SPS {
+                0 => 0.0,
+                idx => symbols[idx - 1].1,
+            };
+            samples.push(Complex32::new(i, q));
+        }
+
+        let mut aligner = OQPSKAligner::new(SPS as f32);
+        let aligned: Vec<_> = samples.iter().map(|val| aligner.tick(*val)).collect();
+        for (idx, (i, q)) in symbols.iter().enumerate().skip(1) {
+            // Symbol center of the aligned stream
+            let sample = aligned[idx * SPS + SPS / 2 + SPS / 2 - 1];
+            assert_eq!((sample.re, sample.im), (*i, *q));
+        }
+    }
+
+    #[test]
+    fn test_differential_qpsk_roundtrip_under_rotation() {
+        let constellation = Constellation::qpsk();
+        let symbols: Vec<u8> = (0..64u8).map(|idx| (idx % 7 + idx / 5) % 4).collect();
+        for rotation in [0.0, PI / 2.0, PI, 0.3] {
+            let mut encoder = DifferentialEncoder::new(&constellation);
+            let mut detector = DifferentialDetector::new(&constellation);
+            let carrier = Complex32::from_polar(0.8, rotation);
+            for (idx, bits) in symbols.iter().enumerate() {
+                let received = encoder.tick(*bits) * carrier;
+                let decided = constellation.decide(detector.tick(received));
+                // The first symbol has no valid predecessor
+                if idx > 0 {
+                    assert_eq!(decided, *bits, "rotation {}", rotation);
+                }
+            }
+        }
+    }
+}
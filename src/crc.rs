@@ -0,0 +1,300 @@
+use std::fmt::Display;
+
+/// Table driven CRC with the usual width/poly/init/refin/refout/xorout
+/// parametrization, `poly` and `init` are given unreflected.
+#[derive(Debug, Clone)]
+pub struct Crc {
+    width: u32,
+    init: u64,
+    reflect_in: bool,
+    reflect_out: bool,
+    xor_out: u64,
+    table: Box<[u64; 256]>,
+}
+
+fn reflect(value: u64, width: u32) -> u64 {
+    value.reverse_bits() >> (64 - width)
+}
+
+fn mask(width: u32) -> u64 {
+    u64::MAX >> (64 - width)
+}
+
+impl Crc {
+    /// `width` in 1..=64 bits, `poly` without the leading x^width term
+    pub fn new(
+        width: u32,
+        poly: u64,
+        init: u64,
+        reflect_in: bool,
+        reflect_out: bool,
+        xor_out: u64,
+    ) -> Option<Self> {
+        if !(1..=64).contains(&width) || (poly | init | xor_out) & !mask(width) != 0 {
+            return None;
+        }
+
+        let mut table = Box::new([0u64; 256]);
+        for (idx, entry) in table.iter_mut().enumerate() {
+            let mut crc = idx as u64;
+            if reflect_in {
+                // Register holds the reflected CRC in its lowest `width` bits
+                let poly = reflect(poly, width);
+                for _ in 0..8 {
+                    crc = if crc & 1 != 0 {
+                        (crc >> 1) ^ poly
+                    } else {
+                        crc >> 1
+                    };
+                }
+            } else {
+                // Register holds the CRC in its highest `width` bits
+                let poly = poly << (64 - width);
+                crc <<= 56;
+                for _ in 0..8 {
+                    crc = if crc & (1 << 63) != 0 {
+                        (crc << 1) ^ poly
+                    } else {
+                        crc << 1
+                    };
+                }
+            }
+            *entry = crc;
+        }
+
+        Some(Self {
+            width,
+            init,
+            reflect_in,
+            reflect_out,
+            xor_out,
+            table,
+        })
+    }
+
+    /// CRC-16/IBM-3740 (CCITT-FALSE), the CCSDS frame error control field
+    pub fn ccitt() -> Self {
+        Self::new(16, 0x1021, 0xFFFF, false, false, 0).unwrap()
+    }
+
+    /// CRC-16/X.25 as used for the HDLC frame check sequence
+    pub fn x25() -> Self {
+        Self::new(16, 0x1021, 0xFFFF, true, true, 0xFFFF).unwrap()
+    }
+
+    /// CRC-32 as used by Ethernet and zlib
+    pub fn crc32() -> Self {
+        Self::new(32, 0x04C11DB7, 0xFFFF_FFFF, true, true, 0xFFFF_FFFF).unwrap()
+    }
+
+    /// Parses `ccitt`, `x25`, `crc32` or `<width>:<poly>:<init>:<refin>:<refout>:<xorout>`
+    /// with hexadecimal poly, init and xorout and `0`/`1` for the reflection flags,
+    /// e.g. `8:07:00:0:0:00` for CRC-8/SMBUS
+    pub fn parse(spec: &str) -> Option<Self> {
+        match spec {
+            "ccitt" => return Some(Self::ccitt()),
+            "x25" => return Some(Self::x25()),
+            "crc32" => return Some(Self::crc32()),
+            _ => {}
+        }
+
+        let mut fields = spec.split(':');
+        let width = fields.next()?.parse().ok()?;
+        let mut hex = || u64::from_str_radix(fields.next()?.trim_start_matches("0x"), 16).ok();
+        let poly = hex()?;
+        let init = hex()?;
+        let reflect_in = hex().filter(|flag| *flag <= 1)? == 1;
+        let reflect_out = hex().filter(|flag| *flag <= 1)? == 1;
+        let xor_out = hex()?;
+        if fields.next().is_some() {
+            return None;
+        }
+        Self::new(width, poly, init, reflect_in, reflect_out, xor_out)
+    }
+
+    /// Number of bytes the CRC occupies in a frame
+    pub fn len_bytes(&self) -> usize {
+        (self.width as usize).div_ceil(8)
+    }
+
+    pub fn checksum(&self, data: &[u8]) -> u64 {
+        let crc = if self.reflect_in {
+            let mut crc = reflect(self.init, self.width);
+            for byte in data {
+                crc = self.table[((crc ^ *byte as u64) & 0xFF) as usize] ^ (crc >> 8);
+            }
+            if self.reflect_out {
+                crc
+            } else {
+                reflect(crc, self.width)
+            }
+        } else {
+            let mut crc = self.init << (64 - self.width);
+            for byte in data {
+                crc = self.table[((crc >> 56) ^ *byte as u64) as usize] ^ (crc << 8);
+            }
+            let crc = crc >> (64 - self.width);
+            if self.reflect_out {
+                reflect(crc, self.width)
+            } else {
+                crc
+            }
+        };
+        (crc ^ self.xor_out) & mask(self.width)
+    }
+
+    /// CRC bytes as appended to a frame: little endian for reflected output
+    /// (as for HDLC), big endian otherwise
+    pub fn to_bytes(&self, crc: u64) -> Vec<u8> {
+        let len = self.len_bytes();
+        if self.reflect_out {
+            crc.to_le_bytes()[..len].to_vec()
+        } else {
+            crc.to_be_bytes()[8 - len..].to_vec()
+        }
+    }
+
+    /// Checks a frame ending with its CRC, the CRC covers all preceding bytes
+    pub fn check(&self, frame: &[u8]) -> bool {
+        match frame.len().checked_sub(self.len_bytes()) {
+            Some(data_len) => self.to_bytes(self.checksum(&frame[..data_len])) == frame[data_len..],
+            None => false,
+        }
+    }
+}
+
+/// CRC validation of decoded frames
+#[derive(Debug, Clone)]
+pub struct FrameCheck {
+    pub crc: Crc,
+    /// First byte covered by the CRC, e.g. to skip a header
+    pub offset: usize,
+    /// Drop frames with a CRC mismatch instead of flagging them
+    pub drop: bool,
+}
+
+impl FrameCheck {
+    /// Parses `<crc>[,<offset>][,drop]`, see `Crc::parse`
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut fields = spec.split(',');
+        let crc = Crc::parse(fields.next()?)?;
+        let mut field = fields.next();
+        let offset = match field {
+            Some(offset) if offset != "drop" => {
+                field = fields.next();
+                offset.parse().ok()?
+            }
+            _ => 0,
+        };
+        let drop = match field {
+            Some("drop") => true,
+            Some(_) => return None,
+            None => false,
+        };
+        if fields.next().is_some() {
+            return None;
+        }
+        Some(Self { crc, offset, drop })
+    }
+
+    pub fn check(&self, frame: &[u8]) -> bool {
+        frame
+            .get(self.offset..)
+            .is_some_and(|covered| self.crc.check(covered))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CrcStats {
+    pub passed: usize,
+    pub failed: usize,
+}
+
+impl CrcStats {
+    pub fn record(&mut self, passed: bool) {
+        if passed {
+            self.passed += 1;
+        } else {
+            self.failed += 1;
+        }
+    }
+}
+
+impl Display for CrcStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CRC: {} passed, {} failed", self.passed, self.failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Crc, FrameCheck};
+
+    const CHECK_INPUT: &[u8] = b"123456789";
+
+    #[test]
+    fn test_crc_check_values() {
+        assert_eq!(Crc::ccitt().checksum(CHECK_INPUT), 0x29B1);
+        assert_eq!(Crc::x25().checksum(CHECK_INPUT), 0x906E);
+        assert_eq!(Crc::crc32().checksum(CHECK_INPUT), 0xCBF43926);
+
+        let cases = [
+            // CRC-8/SMBUS
+            ("8:07:00:0:0:00", 0xF4),
+            // CRC-3/ROHC
+            ("3:3:7:1:1:0", 0x6),
+            // CRC-5/USB
+            ("5:05:1f:1:1:1f", 0x19),
+            // CRC-16/ARC
+            ("16:8005:0000:1:1:0000", 0xBB3D),
+            // CRC-24/OPENPGP
+            ("24:864cfb:b704ce:0:0:000000", 0x21CF02),
+            // CRC-32/BZIP2
+            ("32:04c11db7:ffffffff:0:0:ffffffff", 0xFC891918),
+            // CRC-64/XZ
+            (
+                "64:42f0e1eba9ea3693:ffffffffffffffff:1:1:ffffffffffffffff",
+                0x995DC9BBDF1939FA,
+            ),
+        ];
+        for (spec, check) in cases {
+            assert_eq!(
+                Crc::parse(spec).unwrap().checksum(CHECK_INPUT),
+                check,
+                "{spec}"
+            );
+        }
+
+        assert!(Crc::parse("65:1:0:0:0:0").is_none());
+        assert!(Crc::parse("8:107:00:0:0:00").is_none());
+        assert!(Crc::parse("8:07:00:2:0:00").is_none());
+        assert!(Crc::parse("8:07:00:0:0").is_none());
+    }
+
+    #[test]
+    fn test_frame_check() {
+        let mut frame = vec![0xAA, 0xBB];
+        frame.extend_from_slice(CHECK_INPUT);
+        frame.extend_from_slice(&[0x29, 0xB1]);
+
+        let check = FrameCheck::parse("ccitt,2").unwrap();
+        assert!(!check.drop);
+        assert!(check.check(&frame));
+        assert!(!FrameCheck::parse("ccitt").unwrap().check(&frame));
+        assert!(!check.check(&frame[..3]));
+
+        frame[4] ^= 0x10;
+        assert!(!check.check(&frame));
+
+        // Reflected output is appended little endian
+        let crc = Crc::x25();
+        let mut frame = CHECK_INPUT.to_vec();
+        frame.extend(crc.to_bytes(crc.checksum(CHECK_INPUT)));
+        assert_eq!(&frame[9..], &[0x6E, 0x90]);
+        assert!(FrameCheck::parse("x25,drop").unwrap().check(&frame));
+
+        assert!(FrameCheck::parse("crc32,4,drop").unwrap().drop);
+        assert!(FrameCheck::parse("crc32,drop,4").is_none());
+        assert!(FrameCheck::parse("crc32,x").is_none());
+    }
+}
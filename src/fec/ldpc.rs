@@ -0,0 +1,850 @@
+use crate::soft_bit::SoftBit;
+
+/// LLR of the known zero fill bits, large enough to never be overruled
+const FILL_LLR: f32 = 1e6;
+
+/// Size of the circulants of the CCSDS C2 code
+const C2_CIRCULANT: usize = 511;
+
+/// Positions of the two ones in the first row of each circulant of the CCSDS C2
+/// (8176, 7154) code (CCSDS 131.1-O-2), a 2 x 16 base matrix
+const C2_SHIFTS: [[[usize; 2]; 16]; 2] = [
+    [
+        [0, 176],
+        [12, 239],
+        [0, 352],
+        [24, 431],
+        [0, 392],
+        [151, 409],
+        [0, 351],
+        [9, 359],
+        [0, 307],
+        [53, 329],
+        [0, 207],
+        [18, 281],
+        [0, 399],
+        [202, 457],
+        [0, 247],
+        [36, 261],
+    ],
+    [
+        [99, 471],
+        [130, 473],
+        [198, 435],
+        [260, 478],
+        [215, 420],
+        [282, 481],
+        [48, 396],
+        [193, 445],
+        [273, 430],
+        [302, 451],
+        [96, 379],
+        [191, 386],
+        [244, 467],
+        [364, 470],
+        [51, 382],
+        [192, 414],
+    ],
+];
+
+/// `θ_k` of the AR4JA permutations `Π_1..Π_26` (CCSDS 131.0-B, section 7.4)
+const AR4JA_THETA: [usize; 26] = [
+    3, 0, 1, 2, 2, 3, 0, 1, 0, 1, 2, 0, 2, 3, 0, 1, 2, 0, 1, 2, 0, 1, 2, 1, 2, 3,
+];
+
+/// Blocks of the AR4JA rate 1/2 protograph, each the sum of the listed permutations,
+/// 0 standing for the identity and `k` for `Π_k`. The last block column is punctured.
+const AR4JA_HALF: [[&[usize]; 5]; 3] = [
+    [&[], &[], &[0], &[], &[0, 1]],
+    [&[0], &[0], &[], &[0], &[2, 3, 4]],
+    [&[0], &[5, 6], &[], &[7, 8], &[0]],
+];
+
+/// Block columns put in front of the rate 1/2 protograph for rate 2/3
+const AR4JA_TWO_THIRDS: [[&[usize]; 2]; 3] =
+    [[&[], &[]], [&[9, 10, 11], &[0]], [&[0], &[12, 13, 14]]];
+
+/// Block columns put in front of the rate 2/3 protograph for rate 4/5
+const AR4JA_FOUR_FIFTHS: [[&[usize]; 4]; 3] = [
+    [&[], &[], &[], &[]],
+    [&[21, 22, 23], &[0], &[15, 16, 17], &[0]],
+    [&[0], &[24, 25, 26], &[0], &[18, 19, 20]],
+];
+
+/// Adds a one to a check, overlapping permutations cancel
+fn toggle(check: &mut Vec<usize>, col: usize) {
+    match check.iter().position(|other| *other == col) {
+        Some(idx) => {
+            check.remove(idx);
+        }
+        None => check.push(col),
+    }
+}
+
+/// Code rates of the CCSDS AR4JA LDPC codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ar4jaRate {
+    Half,
+    TwoThirds,
+    FourFifths,
+}
+
+impl Ar4jaRate {
+    pub fn parse(rate: &str) -> Option<Self> {
+        match rate {
+            "1/2" => Some(Self::Half),
+            "2/3" => Some(Self::TwoThirds),
+            "4/5" => Some(Self::FourFifths),
+            _ => None,
+        }
+    }
+
+    /// Size `M` of the permutation blocks for `k` information bits
+    pub fn block_size(&self, k: usize) -> Option<usize> {
+        let scale = match k {
+            1024 => 1,
+            4096 => 4,
+            16384 => 16,
+            _ => return None,
+        };
+        Some(
+            scale
+                * match self {
+                    Self::Half => 512,
+                    Self::TwoThirds => 256,
+                    Self::FourFifths => 128,
+                },
+        )
+    }
+
+    /// Protograph rows, the block columns of the higher rates come first
+    fn protograph(&self) -> Vec<Vec<&'static [usize]>> {
+        (0..3)
+            .map(|row| {
+                let mut blocks = Vec::new();
+                if *self == Self::FourFifths {
+                    blocks.extend(AR4JA_FOUR_FIFTHS[row]);
+                }
+                if *self != Self::Half {
+                    blocks.extend(AR4JA_TWO_THIRDS[row]);
+                }
+                blocks.extend(AR4JA_HALF[row]);
+                blocks
+            })
+            .collect()
+    }
+}
+
+/// Parses the `φ_k(j, M)` values of the AR4JA permutations for one block size `M`:
+/// 26 lines, line `k` holding `φ_k(0, M)` to `φ_k(3, M)` of CCSDS 131.0-B, tables 7-3 to 7-6.
+pub fn parse_ar4ja_phi(text: &str, block_size: usize) -> Option<[[usize; 4]; 26]> {
+    let mut phi = [[0; 4]; 26];
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    for row in phi.iter_mut() {
+        let values: Vec<usize> = lines
+            .next()?
+            .split_whitespace()
+            .map(|value| value.parse().ok().filter(|value| *value < block_size / 4))
+            .collect::<Option<_>>()?;
+        *row = values.try_into().ok()?;
+    }
+    lines.next().is_none().then_some(phi)
+}
+
+/// Sparse binary parity check matrix, stored as the codeword positions of every check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParityCheckMatrix {
+    cols: usize,
+    checks: Vec<Vec<usize>>,
+}
+
+impl ParityCheckMatrix {
+    pub fn new(cols: usize, checks: Vec<Vec<usize>>) -> Self {
+        assert!(
+            checks.iter().flatten().all(|col| *col < cols),
+            "check refers to a column out of range"
+        );
+        Self { cols, checks }
+    }
+
+    /// Parses MacKay's alist format: `n m`, the maximum column and row
+    /// weights, all column weights, all row weights, then the (1 based,
+    /// zero padded) rows of every column followed by the columns of every row.
+    pub fn from_alist(alist: &str) -> Option<Self> {
+        let mut lines = alist.lines().filter(|line| !line.trim().is_empty());
+        let mut numbers = || -> Option<Vec<usize>> {
+            lines
+                .next()?
+                .split_whitespace()
+                .map(|number| number.parse().ok())
+                .collect()
+        };
+        let (cols, rows) = match numbers()?[..] {
+            [cols, rows] => (cols, rows),
+            _ => return None,
+        };
+        numbers()?;
+        let col_weights = numbers()?;
+        let row_weights = numbers()?;
+        if col_weights.len() != cols || row_weights.len() != rows {
+            return None;
+        }
+
+        let mut from_cols = vec![Vec::new(); rows];
+        for col in 0..cols {
+            for row in numbers()?.into_iter().filter(|row| *row != 0) {
+                from_cols.get_mut(row - 1)?.push(col);
+            }
+        }
+        let mut checks = Vec::with_capacity(rows);
+        for weight in row_weights {
+            let check: Vec<usize> = numbers()?
+                .into_iter()
+                .filter(|col| *col != 0)
+                .map(|col| col - 1)
+                .collect();
+            if check.len() != weight || check.iter().any(|col| *col >= cols) {
+                return None;
+            }
+            checks.push(check);
+        }
+
+        // Both halves have to describe the same matrix
+        for (check, from_col) in checks.iter().zip(from_cols.iter()) {
+            let mut check = check.clone();
+            check.sort_unstable();
+            if check != *from_col {
+                return None;
+            }
+        }
+        Some(Self { cols, checks })
+    }
+
+    #[cfg(test)]
+    pub fn to_alist(&self) -> String {
+        let mut col_rows = vec![Vec::new(); self.cols];
+        for (row, check) in self.checks.iter().enumerate() {
+            for col in check {
+                col_rows[*col].push(row);
+            }
+        }
+        let max_col_weight = col_rows.iter().map(Vec::len).max().unwrap_or(0);
+        let max_row_weight = self.checks.iter().map(Vec::len).max().unwrap_or(0);
+        let list = |entries: &[usize], len: usize| {
+            let mut line: Vec<String> = entries.iter().map(|idx| (idx + 1).to_string()).collect();
+            line.resize(len, "0".to_string());
+            line.join(" ")
+        };
+        let weights = |lists: &[Vec<usize>]| {
+            let weights: Vec<String> = lists.iter().map(|list| list.len().to_string()).collect();
+            weights.join(" ")
+        };
+
+        let mut alist = vec![
+            format!("{} {}", self.cols, self.rows()),
+            format!("{} {}", max_col_weight, max_row_weight),
+            weights(&col_rows),
+            weights(&self.checks),
+        ];
+        alist.extend(col_rows.iter().map(|rows| list(rows, max_col_weight)));
+        alist.extend(self.checks.iter().map(|cols| list(cols, max_row_weight)));
+        alist.join("\n") + "\n"
+    }
+
+    /// Gaussian elimination with the pivots taken from the last columns first.
+    /// Returns the pivot columns with their reduced rows as bitsets over all columns.
+    fn reduce(&self) -> Vec<(usize, Vec<u64>)> {
+        let words = self.cols.div_ceil(64);
+        let mut rows: Vec<Vec<u64>> = self
+            .checks
+            .iter()
+            .map(|check| {
+                let mut row = vec![0u64; words];
+                for col in check {
+                    row[col / 64] ^= 1 << (col % 64);
+                }
+                row
+            })
+            .collect();
+
+        let mut pivots = Vec::new();
+        for col in (0..self.cols).rev() {
+            let rank = pivots.len();
+            let bit = |row: &Vec<u64>| row[col / 64] >> (col % 64) & 1 == 1;
+            let pivot = match rows[rank..].iter().position(bit) {
+                None => continue,
+                Some(offset) => rank + offset,
+            };
+            rows.swap(rank, pivot);
+            let pivot_row = rows[rank].clone();
+            for (idx, row) in rows.iter_mut().enumerate() {
+                if idx != rank && bit(row) {
+                    row.iter_mut()
+                        .zip(pivot_row.iter())
+                        .for_each(|(word, pivot)| *word ^= pivot);
+                }
+            }
+            pivots.push(col);
+        }
+
+        pivots.into_iter().zip(rows).collect()
+    }
+
+    /// Columns that are free in the reduced matrix, i.e. that carry the information
+    /// bits of a systematic codeword. There are `cols - rank` of them.
+    pub fn info_cols(&self) -> Vec<usize> {
+        let pivots: Vec<usize> = self.reduce().into_iter().map(|(col, _)| col).collect();
+        (0..self.cols).filter(|col| !pivots.contains(col)).collect()
+    }
+
+    /// Quasi-cyclic matrix from a base matrix of `size x size` circulants,
+    /// each given by the shifts of the identity matrices it sums up
+    /// (empty for a zero block), e.g. the weight 2 circulants of the CCSDS C2 code.
+    pub fn quasi_cyclic(size: usize, base: &[Vec<Vec<usize>>]) -> Self {
+        let block_cols = base.first().map_or(0, Vec::len);
+        let mut checks = Vec::with_capacity(base.len() * size);
+        for block_row in base {
+            assert_eq!(block_row.len(), block_cols, "ragged base matrix");
+            for row in 0..size {
+                let mut check = Vec::new();
+                for (block_col, shifts) in block_row.iter().enumerate() {
+                    for shift in shifts {
+                        let col = block_col * size + (row + shift) % size;
+                        toggle(&mut check, col);
+                    }
+                }
+                checks.push(check);
+            }
+        }
+        Self::new(block_cols * size, checks)
+    }
+
+    /// The CCSDS C2 (8176, 7154) code, usually shortened by 18 fill bits and sent with 2 appended bits
+    pub fn ccsds_c2() -> Self {
+        let base: Vec<Vec<Vec<usize>>> = C2_SHIFTS
+            .iter()
+            .map(|row| row.iter().map(|shifts| shifts.to_vec()).collect())
+            .collect();
+        Self::quasi_cyclic(C2_CIRCULANT, &base)
+    }
+
+    /// CCSDS AR4JA code with permutation blocks of size `block_size` (`M`), the
+    /// `φ_k(j, M)` values are given per permutation, see `parse_ar4ja_phi`.
+    /// The last `M` columns are punctured.
+    pub fn ar4ja(rate: Ar4jaRate, block_size: usize, phi: &[[usize; 4]; 26]) -> Self {
+        assert!(
+            block_size.is_multiple_of(4),
+            "block size has to be a multiple of 4"
+        );
+        let quarter = block_size / 4;
+        // Π_k maps row i to column π_k(i), see CCSDS 131.0-B, section 7.4.2
+        let permute = |k: usize, row: usize| match k {
+            0 => row,
+            k => {
+                let j = row / quarter;
+                quarter * ((AR4JA_THETA[k - 1] + j) % 4) + (phi[k - 1][j] + row) % quarter
+            }
+        };
+
+        let protograph = rate.protograph();
+        let cols = protograph[0].len() * block_size;
+        let mut checks = Vec::with_capacity(3 * block_size);
+        for block_row in protograph {
+            for row in 0..block_size {
+                let mut check = Vec::new();
+                for (block_col, permutations) in block_row.iter().enumerate() {
+                    for k in permutations.iter() {
+                        let col = block_col * block_size + permute(*k, row);
+                        toggle(&mut check, col);
+                    }
+                }
+                checks.push(check);
+            }
+        }
+        Self::new(cols, checks)
+    }
+
+    #[cfg(test)]
+    pub fn rows(&self) -> usize {
+        self.checks.len()
+    }
+
+    #[cfg(test)]
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn is_codeword(&self, bits: &[bool]) -> bool {
+        self.checks
+            .iter()
+            .all(|check| !check.iter().fold(false, |parity, col| parity ^ bits[*col]))
+    }
+}
+
+/// Normalized min-sum decoder with flooding schedule and early termination
+/// once all parity checks are satisfied.
+#[derive(Debug)]
+pub struct LdpcDecoder {
+    h: ParityCheckMatrix,
+    max_iterations: usize,
+    /// Leading codeword bits that are known zeros and not transmitted
+    fill_bits: usize,
+    /// Scaling of the check messages, compensating the min-sum overestimation
+    normalization: f32,
+    /// Codeword position of every edge, in check order
+    edge_cols: Vec<usize>,
+    check_messages: Vec<f32>,
+    totals: Vec<f32>,
+}
+
+impl LdpcDecoder {
+    pub fn new(h: ParityCheckMatrix, max_iterations: usize) -> Self {
+        let edge_cols: Vec<usize> = h.checks.iter().flatten().copied().collect();
+        Self {
+            check_messages: vec![0.0; edge_cols.len()],
+            totals: vec![0.0; h.cols],
+            edge_cols,
+            h,
+            max_iterations,
+            fill_bits: 0,
+            normalization: 0.75,
+        }
+    }
+
+    pub fn with_fill_bits(self, fill_bits: usize) -> Self {
+        assert!(
+            fill_bits < self.h.cols,
+            "more fill bits than the code length"
+        );
+        Self { fill_bits, ..self }
+    }
+
+    /// Decodes the received (soft) bits into `codeword`, they start after the fill bits
+    /// and positions missing at the end of `received` are punctured. Returns the number
+    /// of iterations if a valid codeword was found, `codeword` holds the last hard decisions otherwise.
+    pub fn decode<S: SoftBit>(
+        &mut self,
+        received: &[S],
+        codeword: &mut Vec<bool>,
+    ) -> Option<usize> {
+        assert!(
+            self.fill_bits + received.len() <= self.h.cols,
+            "more bits than the code length"
+        );
+        // Internally LLR = ln(P(0) / P(1)), the usual sign convention of min-sum
+        let channel: Vec<f32> = (0..self.h.cols)
+            .map(|col| match col.checked_sub(self.fill_bits) {
+                None => FILL_LLR,
+                Some(idx) => received.get(idx).map_or(0.0, |bit| -bit.llr()),
+            })
+            .collect();
+        self.check_messages.fill(0.0);
+        self.totals.copy_from_slice(&channel);
+
+        codeword.clear();
+        codeword.resize(self.h.cols, false);
+        for iteration in 0..=self.max_iterations {
+            for (bit, total) in codeword.iter_mut().zip(self.totals.iter()) {
+                *bit = *total < 0.0;
+            }
+            if self.h.is_codeword(codeword) {
+                return Some(iteration);
+            }
+            if iteration == self.max_iterations {
+                break;
+            }
+
+            let mut edge = 0;
+            for check in self.h.checks.iter() {
+                let edges = edge..edge + check.len();
+                edge = edges.end;
+
+                // Variable to check messages exclude the last message of this check
+                let mut min1 = f32::INFINITY;
+                let mut min2 = f32::INFINITY;
+                let mut min_edge = edges.start;
+                let mut negative = false;
+                for idx in edges.clone() {
+                    let message = self.totals[self.edge_cols[idx]] - self.check_messages[idx];
+                    negative ^= message < 0.0;
+                    let magnitude = message.abs();
+                    if magnitude < min1 {
+                        min2 = min1;
+                        min1 = magnitude;
+                        min_edge = idx;
+                    } else if magnitude < min2 {
+                        min2 = magnitude;
+                    }
+                }
+                for idx in edges {
+                    let col = self.edge_cols[idx];
+                    let message = self.totals[col] - self.check_messages[idx];
+                    let magnitude = if idx == min_edge { min2 } else { min1 };
+                    let sign = if negative ^ (message < 0.0) {
+                        -1.0
+                    } else {
+                        1.0
+                    };
+                    self.check_messages[idx] = sign * self.normalization * magnitude;
+                }
+            }
+
+            self.totals.copy_from_slice(&channel);
+            for (col, message) in self.edge_cols.iter().zip(self.check_messages.iter()) {
+                self.totals[*col] += message;
+            }
+        }
+        None
+    }
+}
+
+/// Layout of a (shortened) LDPC codeword within a frame: `fill` known zero information bits
+/// at the start of the codeword and `punctured` bits at its end are not transmitted,
+/// `appended` bits after the codeword are ignored, e.g. 18, 0 and 2 for the CCSDS C2 code.
+#[derive(Debug, Clone)]
+pub struct LdpcCode {
+    pub h: ParityCheckMatrix,
+    /// Codeword positions of the transmitted information bits
+    info_cols: Vec<usize>,
+    pub fill: usize,
+    pub punctured: usize,
+    pub appended: usize,
+}
+
+impl LdpcCode {
+    /// The information bits are the leading whole bytes of the information columns after the
+    /// fill bits, the rank of `h` decides how many columns there are. Left over columns carry
+    /// no data, e.g. the two dimensions of the C2 code that come from redundant checks.
+    /// `None` if the fill bits are no leading information bits or not a byte is left.
+    pub fn new(
+        h: ParityCheckMatrix,
+        fill: usize,
+        punctured: usize,
+        appended: usize,
+    ) -> Option<Self> {
+        let mut info_cols = h.info_cols();
+        let shortened = (0..fill).eq(info_cols.iter().copied().take(fill));
+        let info_len = info_cols.len().checked_sub(fill)? / 8 * 8;
+        if !shortened || info_len == 0 || fill + punctured >= h.cols {
+            return None;
+        }
+        info_cols.drain(..fill);
+        info_cols.truncate(info_len);
+        Some(Self {
+            h,
+            info_cols,
+            fill,
+            punctured,
+            appended,
+        })
+    }
+
+    /// Bits in the frame after the syncword
+    pub fn frame_len(&self) -> usize {
+        self.h.cols - self.fill - self.punctured + self.appended
+    }
+
+    /// Extracts the transmitted information bits from a decoded codeword
+    pub fn info_bits(&self, codeword: &[bool]) -> Vec<bool> {
+        self.info_cols.iter().map(|col| codeword[*col]).collect()
+    }
+}
+
+/// Systematic encoder derived from a parity check matrix by Gaussian elimination,
+/// meant for generating test frames. Parity bits are placed at the last possible
+/// columns, so for the usual codes the information bits come first.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct LdpcEncoder {
+    cols: usize,
+    info_cols: Vec<usize>,
+    /// Parity column and its reduced check row as a bitset over all columns
+    parity_rows: Vec<(usize, Vec<u64>)>,
+}
+
+#[cfg(test)]
+impl LdpcEncoder {
+    pub fn new(h: &ParityCheckMatrix) -> Self {
+        let parity_rows = h.reduce();
+        let info_cols = (0..h.cols)
+            .filter(|col| parity_rows.iter().all(|(pivot, _)| pivot != col))
+            .collect();
+        Self {
+            cols: h.cols,
+            info_cols,
+            parity_rows,
+        }
+    }
+
+    /// Number of information bits
+    pub fn k(&self) -> usize {
+        self.info_cols.len()
+    }
+
+    /// Codeword positions of the information bits
+    pub fn info_cols(&self) -> &[usize] {
+        &self.info_cols
+    }
+
+    pub fn encode(&self, info: &[bool]) -> Vec<bool> {
+        assert_eq!(info.len(), self.k(), "wrong number of information bits");
+        let mut words = vec![0u64; self.cols.div_ceil(64)];
+        for (col, bit) in self.info_cols.iter().zip(info) {
+            words[col / 64] |= (*bit as u64) << (col % 64);
+        }
+        // The reduced rows only contain their pivot and information columns
+        for (col, row) in self.parity_rows.iter() {
+            let parity = row
+                .iter()
+                .zip(words.iter())
+                .map(|(row, word)| (row & word).count_ones())
+                .sum::<u32>();
+            words[col / 64] |= ((parity & 1) as u64) << (col % 64);
+        }
+        (0..self.cols)
+            .map(|col| words[col / 64] >> (col % 64) & 1 == 1)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_ar4ja_phi, Ar4jaRate, LdpcCode, LdpcDecoder, LdpcEncoder, ParityCheckMatrix,
+    };
+    use crate::test_utils::TestRng;
+
+    /// Rate 1/2 (3,6) regular quasi-cyclic code, n = 384
+    fn qc_code() -> ParityCheckMatrix {
+        let base: Vec<Vec<Vec<usize>>> = [
+            [0, 5, 12, 27, 31, 44],
+            [3, 17, 0, 50, 22, 9],
+            [40, 1, 33, 7, 60, 25],
+        ]
+        .iter()
+        .map(|row| row.iter().map(|shift| vec![*shift]).collect())
+        .collect();
+        ParityCheckMatrix::quasi_cyclic(64, &base)
+    }
+
+    #[test]
+    fn test_alist_roundtrip() {
+        // (7,4) Hamming code
+        let alist = "7 3\n3 4\n1 1 2 1 2 2 3\n4 4 4\n\
+                     1 0 0\n2 0 0\n1 3 0\n3 0 0\n1 2 0\n2 3 0\n1 2 3\n\
+                     1 3 5 7\n2 5 6 7\n3 4 6 7\n";
+        let h = ParityCheckMatrix::from_alist(alist).unwrap();
+        assert_eq!((h.rows(), h.cols()), (3, 7));
+        assert_eq!(h.to_alist(), alist);
+        assert_eq!(
+            ParityCheckMatrix::from_alist(&h.to_alist()),
+            Some(h.clone())
+        );
+
+        let encoder = LdpcEncoder::new(&h);
+        assert_eq!(encoder.k(), 4);
+        for value in 0..16 {
+            let info: Vec<bool> = (0..4).map(|idx| value >> idx & 1 == 1).collect();
+            assert!(h.is_codeword(&encoder.encode(&info)));
+        }
+
+        // Inconsistent halves
+        let broken = alist.replace("3 4 6 7", "3 4 5 7");
+        assert_eq!(ParityCheckMatrix::from_alist(&broken), None);
+
+        let h = qc_code();
+        assert_eq!(ParityCheckMatrix::from_alist(&h.to_alist()), Some(h));
+    }
+
+    #[test]
+    fn test_min_sum_decodes_noisy_frames() {
+        let h = qc_code();
+        let encoder = LdpcEncoder::new(&h);
+        // The rows of each block row add up to the all ones vector, two are redundant
+        assert_eq!(encoder.k(), 194);
+        // Systematic with (nearly all of) the information bits first
+        assert_eq!(encoder.info_cols()[..190], (0..190).collect::<Vec<_>>()[..]);
+        let mut decoder = LdpcDecoder::new(h.clone(), 50);
+        let mut rng = TestRng::new(45);
+
+        let mut codeword = Vec::new();
+        let mut raw_errors = 0;
+        let mut max_iterations = 0;
+        for _ in 0..20 {
+            let info: Vec<bool> = (0..encoder.k()).map(|_| rng.next_bool()).collect();
+            let frame = encoder.encode(&info);
+            assert!(h.is_codeword(&frame));
+
+            // Eb/N0 of about 4 dB
+            let received: Vec<f32> = frame
+                .iter()
+                .map(|bit| (if *bit { 1.0 } else { -1.0 } + 0.6 * rng.gaussian()) * 2.0 / 0.36)
+                .collect();
+            raw_errors += received
+                .iter()
+                .zip(frame.iter())
+                .filter(|(llr, bit)| (**llr >= 0.0) != **bit)
+                .count();
+
+            let iterations = decoder.decode(&received, &mut codeword).unwrap();
+            assert_eq!(codeword, frame);
+            max_iterations = max_iterations.max(iterations);
+        }
+        assert!(raw_errors > 200);
+        assert!(max_iterations < 50);
+
+        // Early termination for a clean frame, punctured tail
+        let frame = encoder.encode(&vec![true; encoder.k()]);
+        let received: Vec<bool> = frame.clone();
+        assert_eq!(decoder.decode(&received, &mut codeword), Some(0));
+        assert_eq!(decoder.decode(&received[..380], &mut codeword), Some(1));
+        assert_eq!(codeword, frame);
+    }
+
+    #[test]
+    fn test_shortened_code() {
+        let h = qc_code();
+        // Of the 194 information bits the first 24 bytes are used
+        let code = LdpcCode::new(h.clone(), 0, 0, 0).unwrap();
+        let frame = LdpcEncoder::new(&h).encode(&[vec![true; 192], vec![false; 2]].concat());
+        assert_eq!(code.info_bits(&frame), vec![true; 192]);
+        // Fill bits have to be leading information bits, and a byte has to be left
+        assert!(LdpcCode::new(h.clone(), 194, 0, 0).is_none());
+        assert!(LdpcCode::new(h.clone(), 187, 0, 0).is_none());
+        let code = LdpcCode::new(h.clone(), 2, 0, 2).unwrap();
+        assert_eq!(code.frame_len(), 384);
+
+        let encoder = LdpcEncoder::new(&h);
+        let mut decoder = LdpcDecoder::new(h.clone(), 50).with_fill_bits(2);
+        let mut rng = TestRng::new(46);
+        let mut codeword = Vec::new();
+        for _ in 0..10 {
+            let info: Vec<bool> = (0..192).map(|_| rng.next_bool()).collect();
+            let frame = encoder.encode(&[[false; 2].as_slice(), &info].concat());
+            // The fill bits are not transmitted, the appended bits are random
+            let received: Vec<f32> = frame[2..]
+                .iter()
+                .chain([true, false].iter())
+                .map(|bit| (if *bit { 1.0 } else { -1.0 } + 0.6 * rng.gaussian()) * 2.0 / 0.36)
+                .collect();
+            assert_eq!(received.len(), code.frame_len());
+
+            let transmitted = &received[..received.len() - code.appended];
+            assert!(decoder.decode(transmitted, &mut codeword).is_some());
+            assert_eq!(codeword, frame);
+            assert_eq!(code.info_bits(&codeword), info);
+        }
+    }
+
+    /// BPSK LLRs of the bits, with noise of standard deviation `sigma`
+    fn noisy_llrs<'a>(
+        rng: &'a mut TestRng,
+        bits: impl Iterator<Item = &'a bool> + 'a,
+        sigma: f32,
+    ) -> Vec<f32> {
+        bits.map(|bit| {
+            (if *bit { 1.0 } else { -1.0 } + sigma * rng.gaussian()) * 2.0 / (sigma * sigma)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_ccsds_c2() {
+        let h = ParityCheckMatrix::ccsds_c2();
+        assert_eq!((h.rows(), h.cols()), (1022, 8176));
+        assert!(h.checks.iter().all(|check| check.len() == 32));
+        // No two checks share two columns, i.e. there are no 4-cycles
+        let mut pairs = std::collections::HashSet::new();
+        for check in h.checks.iter() {
+            for (idx, a) in check.iter().enumerate() {
+                for b in check[idx + 1..].iter() {
+                    assert!(pairs.insert((*a.min(b), *a.max(b))));
+                }
+            }
+        }
+        // Two redundant checks, systematic with the information bits first
+        let info_cols = h.info_cols();
+        assert_eq!(info_cols.len(), 7156);
+        assert_eq!(info_cols[..7154], (0..7154).collect::<Vec<_>>()[..]);
+
+        // Shortened by 18 fill bits, with 2 appended bits: 8160 bits on the air
+        let code = LdpcCode::new(h.clone(), 18, 0, 2).unwrap();
+        assert_eq!(code.frame_len(), 8160);
+        let encoder = LdpcEncoder::new(&h);
+        let mut decoder = LdpcDecoder::new(h.clone(), 50).with_fill_bits(18);
+        let mut rng = TestRng::new(47);
+        let data: Vec<bool> = (0..7136).map(|_| rng.next_bool()).collect();
+        let frame = encoder.encode(&[vec![false; 18], data.clone(), vec![false; 2]].concat());
+        // Es/N0 of about 5 dB, some 50 raw bit errors
+        let received = noisy_llrs(
+            &mut rng,
+            frame[18..].iter().chain([false, false].iter()),
+            0.4,
+        );
+        assert_eq!(received.len(), code.frame_len());
+        let raw_errors = received
+            .iter()
+            .zip(frame[18..].iter())
+            .filter(|(llr, bit)| (**llr >= 0.0) != **bit)
+            .count();
+        assert!(raw_errors > 20, "{}", raw_errors);
+
+        let mut codeword = Vec::new();
+        let transmitted = &received[..received.len() - code.appended];
+        assert!(decoder.decode(transmitted, &mut codeword).is_some());
+        assert_eq!(code.info_bits(&codeword), data);
+    }
+
+    #[test]
+    fn test_ar4ja() {
+        // Not the CCSDS permutations, the structure does not depend on them
+        let block_size = 64;
+        let mut rng = TestRng::new(48);
+        let phi_text: Vec<String> = (0..26)
+            .map(|_| {
+                let values: Vec<String> =
+                    (0..4).map(|_| (rng.next_u64() % 16).to_string()).collect();
+                values.join(" ")
+            })
+            .collect();
+        let phi = parse_ar4ja_phi(&phi_text.join("\n"), block_size).unwrap();
+        assert_eq!(
+            parse_ar4ja_phi(&phi_text[..25].join("\n"), block_size),
+            None
+        );
+        assert_eq!(parse_ar4ja_phi(&phi_text.join("\n"), 32), None);
+        assert_eq!(Ar4jaRate::Half.block_size(1024), Some(512));
+        assert_eq!(Ar4jaRate::FourFifths.block_size(16384), Some(2048));
+        assert_eq!(Ar4jaRate::TwoThirds.block_size(2048), None);
+
+        for (rate, blocks, row_weights) in [
+            (Ar4jaRate::Half, 5, [3, 6, 6]),
+            (Ar4jaRate::TwoThirds, 7, [3, 10, 10]),
+            (Ar4jaRate::FourFifths, 11, [3, 18, 18]),
+        ] {
+            let h = ParityCheckMatrix::ar4ja(rate, block_size, &phi);
+            assert_eq!((h.rows(), h.cols()), (3 * block_size, blocks * block_size));
+            for (check, row) in h.checks.iter().zip(0..) {
+                assert_eq!(check.len(), row_weights[row / block_size], "{:?}", rate);
+            }
+            // Full rank, the information bits come first
+            let k = (blocks - 3) * block_size;
+            assert_eq!(h.info_cols(), (0..k).collect::<Vec<_>>(), "{:?}", rate);
+
+            // The last block column is punctured
+            let code = LdpcCode::new(h.clone(), 0, block_size, 0).unwrap();
+            assert_eq!(code.frame_len(), k + 2 * block_size);
+            let info: Vec<bool> = (0..k).map(|_| rng.next_bool()).collect();
+            let frame = LdpcEncoder::new(&h).encode(&info);
+            let received = noisy_llrs(&mut rng, frame[..code.frame_len()].iter(), 0.5);
+            let mut codeword = Vec::new();
+            let mut decoder = LdpcDecoder::new(h.clone(), 50);
+            assert!(
+                decoder.decode(&received, &mut codeword).is_some(),
+                "{:?}",
+                rate
+            );
+            assert_eq!(code.info_bits(&codeword), info);
+        }
+    }
+}
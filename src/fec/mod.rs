@@ -0,0 +1,4 @@
+pub mod ldpc;
+pub mod reed_solomon;
+pub mod turbo;
+pub mod viterbi;
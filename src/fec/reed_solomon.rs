@@ -0,0 +1,450 @@
+/// Columns of Berlekamp's transformation between the conventional and the
+/// dual basis representation used by CCSDS (CCSDS 131.0-B, Annex F)
+const TAL: [u8; 8] = [0x8d, 0xef, 0xec, 0x86, 0xfa, 0x99, 0xaf, 0x7b];
+
+/// GF(2^8) with log/antilog tables
+#[derive(Debug)]
+struct GaloisField {
+    exp: [u8; 510],
+    log: [u8; 256],
+}
+
+impl GaloisField {
+    fn new(poly: u16) -> Self {
+        let mut exp = [0u8; 510];
+        let mut log = [0u8; 256];
+        let mut x = 1u16;
+        for idx in 0..255 {
+            exp[idx] = x as u8;
+            exp[idx + 255] = x as u8;
+            log[x as usize] = idx as u8;
+            x <<= 1;
+            if x & 0x100 != 0 {
+                x ^= poly;
+            }
+        }
+        Self { exp, log }
+    }
+
+    fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        self.exp[self.log[a as usize] as usize + self.log[b as usize] as usize]
+    }
+
+    fn div(&self, a: u8, b: u8) -> u8 {
+        assert!(b != 0, "division by zero in GF(2^8)");
+        if a == 0 {
+            return 0;
+        }
+        self.exp[self.log[a as usize] as usize + 255 - self.log[b as usize] as usize]
+    }
+
+    /// `alpha^power`
+    fn pow(&self, power: usize) -> u8 {
+        self.exp[power % 255]
+    }
+
+    /// Evaluates `poly` (lowest degree first) at `x`
+    fn eval(&self, poly: &[u8], x: u8) -> u8 {
+        poly.iter()
+            .rev()
+            .fold(0, |acc, coeff| self.mul(acc, x) ^ coeff)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RsCorrections {
+    /// Corrected symbols at unknown positions
+    pub errors: usize,
+    /// Symbols at the given erasure positions
+    pub erasures: usize,
+}
+
+impl RsCorrections {
+    pub fn total(&self) -> usize {
+        self.errors + self.erasures
+    }
+}
+
+/// Reed-Solomon codec over GF(2^8), codewords are stored highest degree (first
+/// transmitted symbol) first with the `nroots` parity symbols at the end.
+/// Shortened codewords (virtual fill) are handled by passing fewer than 255 symbols.
+#[derive(Debug)]
+pub struct ReedSolomon {
+    gf: GaloisField,
+    /// Generator polynomial, lowest degree first
+    genpoly: Vec<u8>,
+    fcr: usize,
+    prim: usize,
+    dual_basis: Option<([u8; 256], [u8; 256])>,
+}
+
+impl ReedSolomon {
+    /// The roots of the generator polynomial are `alpha^(prim * (fcr + i))` for `i` in `0..nroots`
+    pub fn new(gf_poly: u16, fcr: usize, prim: usize, nroots: usize) -> Self {
+        let gf = GaloisField::new(gf_poly);
+        let mut genpoly = vec![1u8];
+        for idx in 0..nroots {
+            let root = gf.pow(prim * (fcr + idx));
+            // genpoly *= (x + root)
+            let mut next = vec![0u8; genpoly.len() + 1];
+            for (deg, coeff) in genpoly.iter().enumerate() {
+                next[deg + 1] ^= coeff;
+                next[deg] ^= gf.mul(*coeff, root);
+            }
+            genpoly = next;
+        }
+        Self {
+            gf,
+            genpoly,
+            fcr,
+            prim,
+            dual_basis: None,
+        }
+    }
+
+    /// CCSDS RS(255,223), symbols in the dual basis representation
+    pub fn ccsds() -> Self {
+        Self::ccsds_conventional().with_dual_basis()
+    }
+
+    /// CCSDS RS(255,223) with symbols in the conventional representation
+    pub fn ccsds_conventional() -> Self {
+        Self::new(0x187, 112, 11, 32)
+    }
+
+    /// Converts the symbols from/to Berlekamp's dual basis before/after decoding
+    pub fn with_dual_basis(self) -> Self {
+        let mut to_dual = [0u8; 256];
+        let mut from_dual = [0u8; 256];
+        for (value, dual) in to_dual.iter_mut().enumerate() {
+            *dual = (0..8)
+                .filter(|bit| value & (1 << bit) != 0)
+                .fold(0u8, |acc, bit| acc ^ TAL[7 - bit]);
+            from_dual[*dual as usize] = value as u8;
+        }
+        Self {
+            dual_basis: Some((to_dual, from_dual)),
+            ..self
+        }
+    }
+
+    pub fn nroots(&self) -> usize {
+        self.genpoly.len() - 1
+    }
+
+    /// Computes the parity symbols of `codeword`, its last `nroots` symbols are overwritten
+    #[cfg(test)]
+    pub fn encode(&self, codeword: &mut [u8]) {
+        let nroots = self.nroots();
+        assert!(codeword.len() > nroots && codeword.len() <= 255);
+        let data_len = codeword.len() - nroots;
+        self.convert_from_dual(&mut codeword[..data_len]);
+
+        let mut parity = vec![0u8; nroots];
+        for symbol in codeword[..data_len].iter() {
+            let feedback = symbol ^ parity[0];
+            parity.rotate_left(1);
+            parity[nroots - 1] = 0;
+            if feedback != 0 {
+                for (deg, reg) in parity.iter_mut().enumerate() {
+                    *reg ^= self.gf.mul(feedback, self.genpoly[nroots - 1 - deg]);
+                }
+            }
+        }
+        codeword[data_len..].copy_from_slice(&parity);
+        self.convert_to_dual(codeword);
+    }
+
+    /// Corrects `codeword` in place, `erasures` are known bad symbol positions.
+    /// Returns `None` (leaving the codeword untouched) if it is uncorrectable.
+    pub fn decode(&self, codeword: &mut [u8], erasures: &[usize]) -> Option<RsCorrections> {
+        let nroots = self.nroots();
+        assert!(codeword.len() > nroots && codeword.len() <= 255);
+        if erasures.len() > nroots || erasures.iter().any(|pos| *pos >= codeword.len()) {
+            return None;
+        }
+
+        let mut received = codeword.to_vec();
+        self.convert_from_dual(&mut received);
+
+        let syndromes: Vec<u8> = (0..nroots)
+            .map(|idx| {
+                let root = self.gf.pow(self.prim * (self.fcr + idx));
+                received
+                    .iter()
+                    .fold(0, |acc, symbol| self.gf.mul(acc, root) ^ symbol)
+            })
+            .collect();
+        if syndromes.iter().all(|syndrome| *syndrome == 0) {
+            return Some(RsCorrections::default());
+        }
+
+        // Error locator: X = beta^(n - 1 - pos) with beta = alpha^prim
+        let n = codeword.len();
+        let locator = |pos: usize| self.gf.pow(self.prim * (n - 1 - pos));
+
+        // Erasure locator prod(1 + X_k x) seeds Berlekamp-Massey
+        let mut lambda = vec![1u8];
+        for pos in erasures {
+            lambda = self.poly_mul(&lambda, &[1, locator(*pos)]);
+        }
+        let mut b = lambda.clone();
+        let mut lfsr_len = erasures.len();
+        for r in erasures.len() + 1..=nroots {
+            let discrepancy = (0..r.min(lambda.len())).fold(0u8, |acc, idx| {
+                acc ^ self.gf.mul(lambda[idx], syndromes[r - idx - 1])
+            });
+            b.insert(0, 0);
+            if discrepancy == 0 {
+                continue;
+            }
+
+            let scaled_b: Vec<u8> = b.iter().map(|c| self.gf.mul(*c, discrepancy)).collect();
+            let next = self.poly_add(&lambda, &scaled_b);
+            if 2 * lfsr_len < r + erasures.len() {
+                lfsr_len = r + erasures.len() - lfsr_len;
+                b = lambda
+                    .iter()
+                    .map(|c| self.gf.div(*c, discrepancy))
+                    .collect();
+            }
+            lambda = next;
+        }
+        while lambda.len() > 1 && *lambda.last().unwrap() == 0 {
+            lambda.pop();
+        }
+        let deg_lambda = lambda.len() - 1;
+
+        // Chien search over the (possibly shortened) codeword
+        let error_positions: Vec<usize> = (0..n)
+            .filter(|pos| self.gf.eval(&lambda, self.gf.div(1, locator(*pos))) == 0)
+            .collect();
+        if error_positions.len() != deg_lambda {
+            return None;
+        }
+
+        // Forney: e = X^(1 - fcr) * omega(X^-1) / lambda'(X^-1)
+        let mut omega = self.poly_mul(&syndromes, &lambda);
+        omega.truncate(nroots);
+        let lambda_derivative: Vec<u8> = (1..lambda.len())
+            .map(|deg| if deg % 2 == 1 { lambda[deg] } else { 0 })
+            .collect();
+        for pos in error_positions.iter() {
+            let log_x = self.prim * (n - 1 - pos) % 255;
+            let x_inv = self.gf.pow(255 - log_x);
+            let denominator = self.gf.eval(&lambda_derivative, x_inv);
+            if denominator == 0 {
+                return None;
+            }
+            let scale = self.gf.pow(log_x * ((256 - self.fcr % 255) % 255));
+            let value = self
+                .gf
+                .mul(scale, self.gf.div(self.gf.eval(&omega, x_inv), denominator));
+            received[*pos] ^= value;
+        }
+
+        let erasures_corrected = erasures.len();
+        let corrections = RsCorrections {
+            errors: error_positions.len() - erasures_corrected,
+            erasures: erasures_corrected,
+        };
+        self.convert_to_dual(&mut received);
+        codeword.copy_from_slice(&received);
+        Some(corrections)
+    }
+
+    /// Encodes a frame of `depth` interleaved codewords, symbol `i` belongs to codeword `i % depth`
+    #[cfg(test)]
+    pub fn encode_interleaved(&self, frame: &mut [u8], depth: usize) {
+        assert!(frame.len().is_multiple_of(depth));
+        let mut codeword = vec![0u8; frame.len() / depth];
+        for idx in 0..depth {
+            deinterleave(frame, depth, idx, &mut codeword);
+            self.encode(&mut codeword);
+            interleave(frame, depth, idx, &codeword);
+        }
+    }
+
+    /// Decodes each of the `depth` interleaved codewords in `frame`,
+    /// `erasures` are symbol positions within the frame
+    pub fn decode_interleaved(
+        &self,
+        frame: &mut [u8],
+        depth: usize,
+        erasures: &[usize],
+    ) -> Vec<Option<RsCorrections>> {
+        assert!(frame.len().is_multiple_of(depth));
+        let mut codeword = vec![0u8; frame.len() / depth];
+        (0..depth)
+            .map(|idx| {
+                deinterleave(frame, depth, idx, &mut codeword);
+                let codeword_erasures: Vec<usize> = erasures
+                    .iter()
+                    .filter(|pos| *pos % depth == idx)
+                    .map(|pos| pos / depth)
+                    .collect();
+                let result = self.decode(&mut codeword, &codeword_erasures);
+                interleave(frame, depth, idx, &codeword);
+                result
+            })
+            .collect()
+    }
+
+    fn convert_from_dual(&self, symbols: &mut [u8]) {
+        if let Some((_, from_dual)) = &self.dual_basis {
+            symbols
+                .iter_mut()
+                .for_each(|symbol| *symbol = from_dual[*symbol as usize]);
+        }
+    }
+
+    fn convert_to_dual(&self, symbols: &mut [u8]) {
+        if let Some((to_dual, _)) = &self.dual_basis {
+            symbols
+                .iter_mut()
+                .for_each(|symbol| *symbol = to_dual[*symbol as usize]);
+        }
+    }
+
+    fn poly_mul(&self, a: &[u8], b: &[u8]) -> Vec<u8> {
+        let mut product = vec![0u8; a.len() + b.len() - 1];
+        for (i, a) in a.iter().enumerate() {
+            for (j, b) in b.iter().enumerate() {
+                product[i + j] ^= self.gf.mul(*a, *b);
+            }
+        }
+        product
+    }
+
+    fn poly_add(&self, a: &[u8], b: &[u8]) -> Vec<u8> {
+        (0..a.len().max(b.len()))
+            .map(|idx| a.get(idx).unwrap_or(&0) ^ b.get(idx).unwrap_or(&0))
+            .collect()
+    }
+}
+
+fn deinterleave(frame: &[u8], depth: usize, idx: usize, codeword: &mut [u8]) {
+    for (symbol, value) in codeword
+        .iter_mut()
+        .zip(frame.iter().skip(idx).step_by(depth))
+    {
+        *symbol = *value;
+    }
+}
+
+fn interleave(frame: &mut [u8], depth: usize, idx: usize, codeword: &[u8]) {
+    for (value, symbol) in frame.iter_mut().skip(idx).step_by(depth).zip(codeword) {
+        *value = *symbol;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReedSolomon, RsCorrections};
+    use crate::test_utils::TestRng;
+
+    fn random_codeword(rs: &ReedSolomon, rng: &mut TestRng, len: usize) -> Vec<u8> {
+        let mut codeword: Vec<u8> = (0..len).map(|_| rng.next_u64() as u8).collect();
+        rs.encode(&mut codeword);
+        codeword
+    }
+
+    /// Flips `count` distinct symbols starting at `first`
+    fn corrupt(codeword: &mut [u8], first: usize, count: usize) -> Vec<usize> {
+        let positions: Vec<usize> = (0..count)
+            .map(|idx| (first + idx * 7) % codeword.len())
+            .collect();
+        for pos in positions.iter() {
+            codeword[*pos] ^= 0x5A;
+        }
+        positions
+    }
+
+    #[test]
+    fn test_dual_basis_tables() {
+        let rs = ReedSolomon::ccsds();
+        let (to_dual, from_dual) = rs.dual_basis.unwrap();
+        assert_eq!(&to_dual[..4], &[0x00, 0x7b, 0xaf, 0xd4]);
+        assert!((0..256).all(|value| from_dual[to_dual[value] as usize] as usize == value));
+    }
+
+    #[test]
+    fn test_rs_corrects_up_to_16_errors() {
+        let mut rng = TestRng::new(42);
+        for rs in [ReedSolomon::ccsds(), ReedSolomon::ccsds_conventional()] {
+            let codeword = random_codeword(&rs, &mut rng, 255);
+            let mut clean = codeword.clone();
+            assert_eq!(rs.decode(&mut clean, &[]), Some(RsCorrections::default()));
+
+            let mut received = codeword.clone();
+            corrupt(&mut received, 3, 16);
+            let corrections = rs.decode(&mut received, &[]).unwrap();
+            assert_eq!(corrections.errors, 16);
+            assert_eq!(received, codeword);
+
+            let mut received = codeword.clone();
+            corrupt(&mut received, 3, 17);
+            let corrupted = received.clone();
+            assert_eq!(rs.decode(&mut received, &[]), None);
+            assert_eq!(received, corrupted);
+        }
+    }
+
+    #[test]
+    fn test_rs_errors_and_erasures() {
+        let mut rng = TestRng::new(43);
+        let rs = ReedSolomon::ccsds();
+        let codeword = random_codeword(&rs, &mut rng, 255);
+
+        let mut received = codeword.clone();
+        let errors = corrupt(&mut received, 0, 10);
+        // Twelve erasures, two of them without an actual error
+        let mut erasures: Vec<usize> = corrupt(&mut received, 100, 10);
+        erasures.extend([250, 251]);
+        assert!(erasures.iter().all(|pos| !errors.contains(pos)));
+
+        let corrections = rs.decode(&mut received, &erasures).unwrap();
+        assert_eq!(
+            corrections,
+            RsCorrections {
+                errors: 10,
+                erasures: 12
+            }
+        );
+        assert_eq!(received, codeword);
+    }
+
+    #[test]
+    fn test_rs_interleaved_shortened_frame() {
+        let mut rng = TestRng::new(44);
+        let rs = ReedSolomon::ccsds();
+        const DEPTH: usize = 5;
+        // Shortened to 200 symbols per codeword
+        let mut frame: Vec<u8> = (0..200 * DEPTH).map(|_| rng.next_u64() as u8).collect();
+        rs.encode_interleaved(&mut frame, DEPTH);
+
+        let mut received = frame.clone();
+        // A burst of 40 symbols spreads over all codewords
+        for symbol in received[300..340].iter_mut() {
+            *symbol = !*symbol;
+        }
+        // Codeword 2 additionally gets too many errors
+        for idx in 0..10 {
+            received[2 + DEPTH * (100 + idx)] ^= 1;
+        }
+        let results = rs.decode_interleaved(&mut received, DEPTH, &[]);
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| result.map(|c| c.errors))
+                .collect::<Vec<_>>(),
+            vec![Some(8), Some(8), None, Some(8), Some(8)]
+        );
+        for (idx, (a, b)) in received.iter().zip(frame.iter()).enumerate() {
+            assert!(idx % DEPTH == 2 || a == b);
+        }
+    }
+}
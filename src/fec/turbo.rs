@@ -0,0 +1,460 @@
+use crate::soft_bit::SoftBit;
+
+/// Primes of the CCSDS turbo interleaver
+const INTERLEAVER_PRIMES: [usize; 8] = [31, 37, 43, 47, 53, 59, 61, 67];
+
+/// Forward polynomials `G1`, `G2`, `G3` of the constituent encoders, bit `i` being `D^i`
+const FORWARD_POLYS: [u8; 3] = [0b11011, 0b10101, 0b11111];
+
+/// Trellis steps needed to terminate a constituent encoder
+pub const TAIL_LEN: usize = 4;
+
+const NUM_STATES: usize = 16;
+
+/// CCSDS turbo code rates (CCSDS 131.0-B, section 6)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurboRate {
+    Half,
+    Third,
+    Quarter,
+    Sixth,
+}
+
+/// Transmitted output of a trellis step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    /// Systematic bit `0a`
+    Systematic,
+    /// Parity of constituent encoder a for `G1..G3`
+    A(usize),
+    /// Parity of constituent encoder b for `G1..G3`
+    B(usize),
+}
+
+impl TurboRate {
+    pub fn parse(rate: &str) -> Option<Self> {
+        match rate {
+            "1/2" => Some(Self::Half),
+            "1/3" => Some(Self::Third),
+            "1/4" => Some(Self::Quarter),
+            "1/6" => Some(Self::Sixth),
+            _ => None,
+        }
+    }
+
+    /// Transmitted bits per trellis step
+    pub fn symbols_per_bit(&self) -> usize {
+        match self {
+            Self::Half => 2,
+            Self::Third => 3,
+            Self::Quarter => 4,
+            Self::Sixth => 6,
+        }
+    }
+
+    /// Attached sync marker preceding the codewords as hex digits, the markers for
+    /// rates 1/4 and 1/6 are those for 1/2 and 1/3 followed by their complement
+    pub fn asm(&self) -> &'static str {
+        match self {
+            Self::Half => "034776C7272895B0",
+            Self::Third => "25D5C0CE8990F6C9461BF79C",
+            Self::Quarter => "034776C7272895B0FCB88938D8D76A4F",
+            Self::Sixth => "25D5C0CE8990F6C9461BF79CDA2A3F31766F0936B9E40863",
+        }
+    }
+
+    /// Codeword length for `k` information bits, including the termination
+    pub fn codeword_len(&self, k: usize) -> usize {
+        (k + TAIL_LEN) * self.symbols_per_bit()
+    }
+
+    /// Outputs transmitted in trellis step `step`, rate 1/2 alternates between `1a` and `1b`
+    fn outputs(&self, step: usize) -> &'static [Output] {
+        use Output::*;
+        match self {
+            Self::Half if step.is_multiple_of(2) => &[Systematic, A(0)],
+            Self::Half => &[Systematic, B(0)],
+            Self::Third => &[Systematic, A(0), B(0)],
+            Self::Quarter => &[Systematic, A(1), A(2), B(0)],
+            Self::Sixth => &[Systematic, A(0), A(1), A(2), B(0), B(2)],
+        }
+    }
+}
+
+/// 16 state recursive systematic constituent encoder, feedback `G0 = 1 + D^3 + D^4`.
+/// Bit `i` of the state is the register value `i + 1` steps back.
+#[derive(Debug, Clone, Copy)]
+struct Trellis {
+    next_state: [[u8; 2]; NUM_STATES],
+    parity: [[[bool; 3]; 2]; NUM_STATES],
+}
+
+impl Trellis {
+    fn new() -> Self {
+        let mut next_state = [[0; 2]; NUM_STATES];
+        let mut parity = [[[false; 3]; 2]; NUM_STATES];
+        for state in 0..NUM_STATES {
+            for input in 0..2 {
+                let register = Self::register(state, input);
+                next_state[state][input] = (register & 0xF) as u8;
+                for (output, poly) in FORWARD_POLYS.iter().enumerate() {
+                    parity[state][input][output] =
+                        (register & *poly as usize).count_ones() & 1 == 1;
+                }
+            }
+        }
+        Self { next_state, parity }
+    }
+
+    /// Register contents with the new value as bit 0
+    fn register(state: usize, input: usize) -> usize {
+        (state << 1) | (input ^ Self::tail_input(state))
+    }
+
+    /// Feedback `D^3 + D^4`, as input it drives the register back to the zero state
+    fn tail_input(state: usize) -> usize {
+        ((state >> 2) ^ (state >> 3)) & 1
+    }
+}
+
+/// CCSDS turbo interleaver for `k = 8 * 223 * I` bits, `I = 1, 2, 4, 5`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurboInterleaver {
+    /// Input position of the `n`-th interleaved bit
+    permutation: Vec<usize>,
+}
+
+impl TurboInterleaver {
+    pub fn ccsds(k: usize) -> Option<Self> {
+        const K1: usize = 8;
+        if ![1784, 3568, 7136, 8920].contains(&k) {
+            return None;
+        }
+        let k2 = k / K1;
+        let permutation = (0..k)
+            .map(|s| {
+                let m = s % 2;
+                let i = s / (2 * k2);
+                let j = s / 2 - i * k2;
+                let t = (19 * i + 1) % (K1 / 2);
+                let q = t % 8;
+                let c = (INTERLEAVER_PRIMES[q] * j + 21 * m) % k2;
+                2 * (t + c * K1 / 2 + 1) - m - 1
+            })
+            .collect();
+        Some(Self { permutation })
+    }
+
+    pub fn len(&self) -> usize {
+        self.permutation.len()
+    }
+
+    pub fn interleave<T: Copy>(&self, input: &[T]) -> Vec<T> {
+        self.permutation.iter().map(|idx| input[*idx]).collect()
+    }
+
+    pub fn deinterleave<T: Copy + Default>(&self, input: &[T]) -> Vec<T> {
+        let mut output = vec![T::default(); input.len()];
+        for (value, idx) in input.iter().zip(self.permutation.iter()) {
+            output[*idx] = *value;
+        }
+        output
+    }
+}
+
+/// CCSDS turbo encoder, both constituent encoders are terminated in the same
+/// `TAIL_LEN` steps. During termination the systematic output carries the
+/// tail input of encoder a, the tail input of encoder b is not transmitted.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct TurboEncoder {
+    rate: TurboRate,
+    interleaver: TurboInterleaver,
+    trellis: Trellis,
+}
+
+#[cfg(test)]
+impl TurboEncoder {
+    pub fn new(k: usize, rate: TurboRate) -> Option<Self> {
+        Some(Self {
+            rate,
+            interleaver: TurboInterleaver::ccsds(k)?,
+            trellis: Trellis::new(),
+        })
+    }
+
+    /// Codeword length in bits
+    pub fn codeword_len(&self) -> usize {
+        self.rate.codeword_len(self.interleaver.len())
+    }
+
+    pub fn encode(&self, info: &[bool]) -> Vec<bool> {
+        assert_eq!(info.len(), self.interleaver.len(), "wrong frame length");
+        let interleaved = self.interleaver.interleave(info);
+        let mut codeword = Vec::with_capacity(self.codeword_len());
+        let (mut state_a, mut state_b) = (0, 0);
+        for step in 0..info.len() + TAIL_LEN {
+            let (input_a, input_b) = match step < info.len() {
+                true => (info[step] as usize, interleaved[step] as usize),
+                false => (Trellis::tail_input(state_a), Trellis::tail_input(state_b)),
+            };
+            let parity_a = self.trellis.parity[state_a][input_a];
+            let parity_b = self.trellis.parity[state_b][input_b];
+            for output in self.rate.outputs(step) {
+                codeword.push(match output {
+                    Output::Systematic => input_a == 1,
+                    Output::A(poly) => parity_a[*poly],
+                    Output::B(poly) => parity_b[*poly],
+                });
+            }
+            state_a = self.trellis.next_state[state_a][input_a] as usize;
+            state_b = self.trellis.next_state[state_b][input_b] as usize;
+        }
+        codeword
+    }
+}
+
+/// Iterative max-log-MAP turbo decoder
+#[derive(Debug)]
+pub struct TurboDecoder {
+    rate: TurboRate,
+    interleaver: TurboInterleaver,
+    trellis: Trellis,
+    max_iterations: usize,
+    /// Scaling of the exchanged extrinsic information, compensating the max-log approximation
+    extrinsic_scale: f32,
+}
+
+impl TurboDecoder {
+    pub fn new(k: usize, rate: TurboRate, max_iterations: usize) -> Option<Self> {
+        Some(Self {
+            rate,
+            interleaver: TurboInterleaver::ccsds(k)?,
+            trellis: Trellis::new(),
+            max_iterations,
+            extrinsic_scale: 0.7,
+        })
+    }
+
+    pub fn codeword_len(&self) -> usize {
+        self.rate.codeword_len(self.interleaver.len())
+    }
+
+    /// Decodes the received (soft) codeword into the information bits. Returns the
+    /// number of iterations once the decisions stopped changing, `None` if they
+    /// still changed in the last of `max_iterations` iterations.
+    pub fn decode<S: SoftBit>(&self, received: &[S], info: &mut Vec<bool>) -> Option<usize> {
+        assert_eq!(received.len(), self.codeword_len(), "wrong codeword length");
+        let k = self.interleaver.len();
+        let steps = k + TAIL_LEN;
+
+        // Depuncture into the systematic and parity LLRs of both encoders
+        let mut systematic = vec![0.0; steps];
+        let mut parity_a = vec![[0.0; 3]; steps];
+        let mut parity_b = vec![[0.0; 3]; steps];
+        let mut received = received.iter().map(|bit| bit.llr());
+        for step in 0..steps {
+            for output in self.rate.outputs(step) {
+                let llr = received.next().unwrap();
+                match output {
+                    Output::Systematic => systematic[step] = llr,
+                    Output::A(poly) => parity_a[step][*poly] = llr,
+                    Output::B(poly) => parity_b[step][*poly] = llr,
+                }
+            }
+        }
+        let mut systematic_b = self.interleaver.interleave(&systematic[..k]);
+        systematic_b.resize(steps, 0.0);
+
+        let mut extrinsic_b = vec![0.0; k];
+        info.clear();
+        for iteration in 1..=self.max_iterations {
+            let apriori_a = self.interleaver.deinterleave(&extrinsic_b);
+            let posterior_a = self.max_log_map(&systematic, &apriori_a, &parity_a);
+            let extrinsic_a: Vec<f32> = (0..k)
+                .map(|idx| {
+                    self.extrinsic_scale * (posterior_a[idx] - systematic[idx] - apriori_a[idx])
+                })
+                .collect();
+
+            let apriori_b = self.interleaver.interleave(&extrinsic_a);
+            let posterior_b = self.max_log_map(&systematic_b, &apriori_b, &parity_b);
+            extrinsic_b = (0..k)
+                .map(|idx| {
+                    self.extrinsic_scale * (posterior_b[idx] - systematic_b[idx] - apriori_b[idx])
+                })
+                .collect();
+
+            let decisions: Vec<bool> = self
+                .interleaver
+                .deinterleave(&posterior_b)
+                .iter()
+                .map(|llr| *llr >= 0.0)
+                .collect();
+            let converged = decisions == *info;
+            *info = decisions;
+            if converged {
+                return Some(iteration);
+            }
+        }
+        None
+    }
+
+    /// A posteriori LLRs of the `k` information bits of a terminated constituent code
+    fn max_log_map(&self, systematic: &[f32], apriori: &[f32], parity: &[[f32; 3]]) -> Vec<f32> {
+        let k = apriori.len();
+        let steps = systematic.len();
+        let trellis = &self.trellis;
+        let gamma = |step: usize, state: usize, input: usize| {
+            let input_llr = systematic[step] + apriori.get(step).copied().unwrap_or(0.0);
+            let mut metric = if input == 1 { input_llr } else { -input_llr };
+            for (bit, llr) in trellis.parity[state][input].iter().zip(parity[step].iter()) {
+                metric += if *bit { *llr } else { -*llr };
+            }
+            metric / 2.0
+        };
+        // Tail steps only allow the transition towards the zero state
+        let inputs = |step: usize, state: usize| match step < k {
+            true => 0..2,
+            false => Trellis::tail_input(state)..Trellis::tail_input(state) + 1,
+        };
+
+        let mut alpha = vec![[f32::NEG_INFINITY; NUM_STATES]; steps + 1];
+        alpha[0][0] = 0.0;
+        for step in 0..steps {
+            for state in 0..NUM_STATES {
+                if alpha[step][state] == f32::NEG_INFINITY {
+                    continue;
+                }
+                for input in inputs(step, state) {
+                    let next = trellis.next_state[state][input] as usize;
+                    let metric = alpha[step][state] + gamma(step, state, input);
+                    alpha[step + 1][next] = alpha[step + 1][next].max(metric);
+                }
+            }
+        }
+
+        let mut beta = [f32::NEG_INFINITY; NUM_STATES];
+        beta[0] = 0.0;
+        let mut posterior = vec![0.0; k];
+        for step in (0..steps).rev() {
+            let mut prev_beta = [f32::NEG_INFINITY; NUM_STATES];
+            let mut best = [f32::NEG_INFINITY; 2];
+            for state in 0..NUM_STATES {
+                for input in inputs(step, state) {
+                    let next = trellis.next_state[state][input] as usize;
+                    let metric = gamma(step, state, input) + beta[next];
+                    prev_beta[state] = prev_beta[state].max(metric);
+                    best[input] = best[input].max(alpha[step][state] + metric);
+                }
+            }
+            if step < k {
+                posterior[step] = best[1] - best[0];
+            }
+            // Keep the metrics bounded
+            let max = prev_beta.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            beta = prev_beta.map(|metric| metric - max);
+        }
+        posterior
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TurboDecoder, TurboEncoder, TurboInterleaver, TurboRate, TAIL_LEN};
+    use crate::test_utils::TestRng;
+
+    #[test]
+    fn test_turbo_asm() {
+        // CCSDS 131.0-B attached sync markers for the turbo codes
+        let cases = [
+            (TurboRate::Half, 64, 0x034776C7272895B0u128),
+            (TurboRate::Third, 96, 0x25D5C0CE8990F6C9461BF79C),
+            (TurboRate::Quarter, 128, 0x034776C7272895B0FCB88938D8D76A4F),
+        ];
+        for (rate, len, asm) in cases {
+            assert_eq!(rate.asm().len() * 4, len);
+            assert_eq!(u128::from_str_radix(rate.asm(), 16), Ok(asm));
+        }
+        assert_eq!(
+            TurboRate::Sixth.asm(),
+            "25D5C0CE8990F6C9461BF79CDA2A3F31766F0936B9E40863"
+        );
+    }
+
+    #[test]
+    fn test_interleaver_is_permutation() {
+        for k in [1784, 3568, 7136, 8920] {
+            let interleaver = TurboInterleaver::ccsds(k).unwrap();
+            let mut seen = vec![false; k];
+            for idx in interleaver.permutation.iter() {
+                assert!(!seen[*idx], "k = {}", k);
+                seen[*idx] = true;
+            }
+            let input: Vec<usize> = (0..k).collect();
+            assert_eq!(
+                interleaver.deinterleave(&interleaver.interleave(&input)),
+                input
+            );
+        }
+        assert_eq!(TurboInterleaver::ccsds(1000), None);
+    }
+
+    #[test]
+    fn test_encoder_terminates_and_is_systematic() {
+        let encoder = TurboEncoder::new(1784, TurboRate::Third).unwrap();
+        let mut rng = TestRng::new(46);
+        let info: Vec<bool> = (0..1784).map(|_| rng.next_bool()).collect();
+        let codeword = encoder.encode(&info);
+        assert_eq!(codeword.len(), (1784 + TAIL_LEN) * 3);
+        let systematic: Vec<bool> = codeword.iter().step_by(3).copied().collect();
+        assert_eq!(systematic[..1784], info[..]);
+
+        // All zero input stays in the zero state, including the tail
+        assert!(encoder.encode(&[false; 1784]).iter().all(|bit| !bit));
+    }
+
+    #[test]
+    fn test_turbo_decodes_noisy_frames() {
+        let mut rng = TestRng::new(47);
+        // Eb/N0 of 1 dB
+        for (rate, sigma) in [
+            (TurboRate::Half, 0.89),
+            (TurboRate::Third, 1.09),
+            (TurboRate::Quarter, 1.26),
+            (TurboRate::Sixth, 1.54),
+        ] {
+            let encoder = TurboEncoder::new(1784, rate).unwrap();
+            let info: Vec<bool> = (0..1784).map(|_| rng.next_bool()).collect();
+            let received: Vec<f32> = encoder
+                .encode(&info)
+                .iter()
+                .map(|bit| {
+                    (if *bit { 1.0 } else { -1.0 } + sigma * rng.gaussian()) * 2.0 / (sigma * sigma)
+                })
+                .collect();
+            let raw_errors = |decoded: &[bool]| {
+                decoded
+                    .iter()
+                    .zip(info.iter())
+                    .filter(|(a, b)| a != b)
+                    .count()
+            };
+
+            let mut decoded = Vec::new();
+            TurboDecoder::new(1784, rate, 1)
+                .unwrap()
+                .decode(&received, &mut decoded);
+            let single_iteration_errors = raw_errors(&decoded);
+
+            let decoder = TurboDecoder::new(1784, rate, 20).unwrap();
+            assert!(
+                decoder.decode(&received, &mut decoded).is_some(),
+                "{:?}",
+                rate
+            );
+            assert_eq!(raw_errors(&decoded), 0, "{:?}", rate);
+            assert!(single_iteration_errors > 0, "{:?}", rate);
+        }
+    }
+}
@@ -0,0 +1,569 @@
+use crate::soft_bit::SoftBit;
+use crate::PT1;
+
+/// Feed-forward convolutional code of rate `1/n`.
+///
+/// Generator polynomials are given in the usual octal notation, the MSB tap
+/// being the current input bit, e.g. `0o171` = `1111001`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvolutionalCode {
+    constraint_len: usize,
+    polys: Vec<u32>,
+    /// Outputs transmitted inverted
+    inverted: Vec<bool>,
+}
+
+impl ConvolutionalCode {
+    /// Constraint lengths up to 7 are supported (64 trellis states)
+    pub fn new(constraint_len: usize, polys: &[u32]) -> Option<Self> {
+        let valid = (3..=7).contains(&constraint_len)
+            && polys.len() >= 2
+            && polys
+                .iter()
+                .all(|poly| *poly != 0 && *poly < 1 << constraint_len);
+        valid.then(|| Self {
+            constraint_len,
+            polys: polys.to_vec(),
+            inverted: vec![false; polys.len()],
+        })
+    }
+
+    /// NASA standard k=7 r=1/2 code, `G1 = 171`, `G2 = 133` (octal)
+    pub fn nasa() -> Self {
+        Self::new(7, &[0o171, 0o133]).unwrap()
+    }
+
+    /// The NASA code with the inverted `G2` output of CCSDS 131.0-B
+    pub fn ccsds() -> Self {
+        Self::nasa().with_inverted_output(1)
+    }
+
+    pub fn with_inverted_output(mut self, output: usize) -> Self {
+        self.inverted[output] = true;
+        self
+    }
+
+    /// Parses a comma separated list of octal polynomials with k=7,
+    /// a `~` prefix marks an inverted output, e.g. `171,~133`
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut polys = Vec::new();
+        let mut inverted = Vec::new();
+        for poly in spec.split(',') {
+            let (poly, invert) = match poly.strip_prefix('~') {
+                Some(poly) => (poly, true),
+                None => (poly, false),
+            };
+            polys.push(u32::from_str_radix(poly, 8).ok()?);
+            inverted.push(invert);
+        }
+        let mut code = Self::new(7, &polys)?;
+        code.inverted = inverted;
+        Some(code)
+    }
+
+    pub fn constraint_len(&self) -> usize {
+        self.constraint_len
+    }
+
+    /// Number of coded bits per input bit
+    pub fn outputs(&self) -> usize {
+        self.polys.len()
+    }
+
+    /// Coded bit `output` for the shift register contents, the current
+    /// input being the MSB of the `constraint_len` register bits
+    fn output(&self, register: u32, output: usize) -> bool {
+        ((register & self.polys[output]).count_ones() & 1 == 1) ^ self.inverted[output]
+    }
+}
+
+/// Puncturing pattern of a rate 1/2 mother code, one row per encoder output.
+/// Within a period the kept bits are transmitted column by column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puncturing {
+    pattern: [Vec<bool>; 2],
+}
+
+impl Puncturing {
+    pub fn none() -> Self {
+        Self::from_rows("1", "1")
+    }
+
+    /// Standard patterns as used by CCSDS 131.0-B and DVB-S:
+    /// `1/2`, `2/3`, `3/4`, `5/6` and `7/8`
+    pub fn parse(rate: &str) -> Option<Self> {
+        Some(match rate {
+            "1/2" => Self::none(),
+            "2/3" => Self::from_rows("10", "11"),
+            "3/4" => Self::from_rows("101", "110"),
+            "5/6" => Self::from_rows("10101", "11010"),
+            "7/8" => Self::from_rows("1000101", "1111010"),
+            _ => return None,
+        })
+    }
+
+    fn from_rows(c1: &str, c2: &str) -> Self {
+        let row = |row: &str| row.chars().map(|c| c == '1').collect();
+        Self {
+            pattern: [row(c1), row(c2)],
+        }
+    }
+
+    /// Input bits per puncturing period
+    pub fn period(&self) -> usize {
+        self.pattern[0].len()
+    }
+
+    /// Transmitted bits per puncturing period
+    pub fn transmitted(&self) -> usize {
+        self.pattern.iter().flatten().filter(|keep| **keep).count()
+    }
+
+    /// `(input bit, encoder output)` of every transmitted bit within a period
+    fn transmitted_positions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.period()).flat_map(move |pos| {
+            (0..2).filter_map(move |output| self.pattern[output][pos].then_some((pos, output)))
+        })
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug)]
+pub struct ConvolutionalEncoder {
+    code: ConvolutionalCode,
+    puncturing: Puncturing,
+    register: u32,
+    pos: usize,
+}
+
+#[cfg(test)]
+impl ConvolutionalEncoder {
+    /// Puncturing needs a rate 1/2 code
+    pub fn new(code: ConvolutionalCode, puncturing: Puncturing) -> Self {
+        assert!(
+            code.outputs() == 2 || puncturing == Puncturing::none(),
+            "puncturing needs a rate 1/2 code"
+        );
+        Self {
+            code,
+            puncturing,
+            register: 0,
+            pos: 0,
+        }
+    }
+
+    /// Appends the transmitted coded bits for `bit` to `out`
+    pub fn tick(&mut self, bit: bool, out: &mut Vec<bool>) {
+        let k = self.code.constraint_len;
+        self.register = (self.register >> 1) | ((bit as u32) << (k - 1));
+        for output in 0..self.code.outputs() {
+            if self.code.outputs() != 2 || self.puncturing.pattern[output][self.pos] {
+                out.push(self.code.output(self.register, output));
+            }
+        }
+        self.pos = (self.pos + 1) % self.puncturing.period();
+    }
+}
+
+/// Streaming soft decision Viterbi decoder, every step takes the LLRs of the
+/// `n` coded bits of one input bit (0 for punctured bits) and, once the
+/// traceback is filled, returns the decision `traceback` steps back.
+#[derive(Debug)]
+pub struct ViterbiDecoder {
+    constraint_len: usize,
+    outputs: usize,
+    /// Expected coded bit as `±1` for every register value and output
+    expected: Vec<f32>,
+    metrics: Vec<f32>,
+    next_metrics: Vec<f32>,
+    /// Survivor decisions, one bit per state and step
+    decisions: Vec<u64>,
+    head: usize,
+    steps: usize,
+    /// Smoothed metric growth relative to the received reliability,
+    /// 1 for a noise free, correctly aligned stream
+    quality: PT1<f32>,
+}
+
+impl ViterbiDecoder {
+    pub fn new(code: &ConvolutionalCode, traceback: usize) -> Self {
+        let num_states = 1 << (code.constraint_len - 1);
+        let expected = (0..1u32 << code.constraint_len)
+            .flat_map(|register| {
+                (0..code.outputs()).map(move |output| {
+                    if code.output(register, output) {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                })
+            })
+            .collect();
+        Self {
+            constraint_len: code.constraint_len,
+            outputs: code.outputs(),
+            expected,
+            metrics: vec![0.0; num_states],
+            next_metrics: vec![0.0; num_states],
+            decisions: vec![0; traceback.max(1)],
+            head: 0,
+            steps: 0,
+            quality: PT1 {
+                alpha: 0.01,
+                current: 0.0,
+            },
+        }
+    }
+
+    /// Smoothing of `quality`, higher code rates need longer averaging
+    pub fn with_quality_alpha(mut self, alpha: f32) -> Self {
+        self.quality.alpha = alpha;
+        self
+    }
+
+    pub fn quality(&self) -> f32 {
+        self.quality.current
+    }
+
+    pub fn step(&mut self, llrs: &[f32]) -> Option<bool> {
+        let k = self.constraint_len;
+        let state_mask = (1 << (k - 1)) - 1;
+        let branch_metric = |register: usize| {
+            let expected = &self.expected[register * self.outputs..][..self.outputs];
+            expected
+                .iter()
+                .zip(llrs)
+                .map(|(e, llr)| e * llr)
+                .sum::<f32>()
+        };
+
+        let mut decisions = 0u64;
+        let mut best = f32::NEG_INFINITY;
+        for (state, next_metric) in self.next_metrics.iter_mut().enumerate() {
+            let input = state >> (k - 2);
+            let prev = (state << 1) & state_mask;
+            // The two predecessors differ in the oldest register bit
+            let metric0 = self.metrics[prev] + branch_metric((input << (k - 1)) | prev);
+            let metric1 = self.metrics[prev | 1] + branch_metric((input << (k - 1)) | prev | 1);
+            *next_metric = if metric1 > metric0 {
+                decisions |= 1 << state;
+                metric1
+            } else {
+                metric0
+            };
+            best = best.max(*next_metric);
+        }
+        // Renormalize, the best metric of the last step was 0
+        for metric in self.next_metrics.iter_mut() {
+            *metric -= best;
+        }
+        std::mem::swap(&mut self.metrics, &mut self.next_metrics);
+
+        let reliability: f32 = llrs.iter().map(|llr| llr.abs()).sum();
+        if reliability > 0.0 {
+            self.quality.tick(best / reliability);
+        }
+
+        let len = self.decisions.len();
+        self.head = (self.head + 1) % len;
+        self.decisions[self.head] = decisions;
+        self.steps += 1;
+        if self.steps < len {
+            return None;
+        }
+
+        let mut state = self
+            .metrics
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(state, _)| state)
+            .unwrap();
+        let mut idx = self.head;
+        for _ in 1..len {
+            let decision = (self.decisions[idx] >> state) as usize & 1;
+            state = ((state << 1) & state_mask) | decision;
+            idx = (idx + len - 1) % len;
+        }
+        Some(state >> (k - 2) == 1)
+    }
+
+    pub fn reset(&mut self) {
+        self.metrics.fill(0.0);
+        self.head = 0;
+        self.steps = 0;
+        self.quality.current = 0.0;
+    }
+}
+
+/// Alignment of the received stream to the code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodePhase {
+    /// Received bits to skip to the start of a puncturing period
+    pub offset: usize,
+    /// QPSK symbols rotated by 90°, i.e. the bit pairs `(a, b)` received as `(!b, a)`.
+    /// 180° rotations only invert the decoded bits, as both NASA polynomials have odd weight.
+    pub rotated: bool,
+}
+
+#[derive(Debug)]
+struct PhaseCandidate {
+    phase: CodePhase,
+    skipped: usize,
+    pos: usize,
+    llrs: [f32; 2],
+    viterbi: ViterbiDecoder,
+}
+
+/// Soft Viterbi decoder for possibly punctured rate 1/2 codes, which resolves
+/// the code phase by decoding all alignments in parallel and following the
+/// one with the best path metric growth.
+#[derive(Debug)]
+pub struct ConvolutionalDecoder {
+    /// `(input bit, encoder output)` of every transmitted bit, see `Puncturing`
+    positions: Vec<(usize, usize)>,
+    candidates: Vec<PhaseCandidate>,
+    active: usize,
+    /// Quality margin needed before switching to another code phase
+    hysteresis: f32,
+    /// First bit of a QPSK symbol, waiting for its partner
+    pending: Option<f32>,
+    /// Second bit of the last derotated QPSK symbol
+    deferred: Option<f32>,
+}
+
+impl ConvolutionalDecoder {
+    pub fn new(code: &ConvolutionalCode, puncturing: &Puncturing, qpsk: bool) -> Self {
+        assert_eq!(code.outputs(), 2, "only rate 1/2 codes are supported");
+        let traceback = if *puncturing == Puncturing::none() {
+            5 * code.constraint_len()
+        } else {
+            12 * code.constraint_len()
+        };
+        let rotations: &[bool] = if qpsk { &[false, true] } else { &[false] };
+        let candidates = rotations
+            .iter()
+            .flat_map(|rotated| {
+                (0..puncturing.transmitted()).map(|offset| PhaseCandidate {
+                    phase: CodePhase {
+                        offset,
+                        rotated: *rotated,
+                    },
+                    skipped: 0,
+                    pos: 0,
+                    llrs: [0.0; 2],
+                    viterbi: ViterbiDecoder::new(code, traceback)
+                        .with_quality_alpha(0.01 / puncturing.period() as f32),
+                })
+            })
+            .collect();
+        Self {
+            positions: puncturing.transmitted_positions().collect(),
+            candidates,
+            active: 0,
+            hysteresis: 0.01 / puncturing.period() as f32,
+            pending: None,
+            deferred: None,
+        }
+    }
+
+    pub fn code_phase(&self) -> CodePhase {
+        self.candidates[self.active].phase
+    }
+
+    /// Smoothed path metric growth of the followed code phase, 1 for a noise free stream
+    pub fn quality(&self) -> f32 {
+        self.candidates[self.active].viterbi.quality()
+    }
+
+    /// Takes one received coded bit, decoded bits are hard decisions
+    pub fn tick<S: SoftBit>(&mut self, bit: S) -> Option<S> {
+        let llr = bit.llr();
+        // Undo the 90° QPSK rotation (a, b) -> (!b, a) for the rotated candidates,
+        // delayed by a bit so every candidate takes at most one bit per tick
+        let rotated_llr = match self.pending.take() {
+            None => {
+                self.pending = Some(llr);
+                self.deferred.take()
+            }
+            Some(first) => {
+                self.deferred = Some(-first);
+                Some(llr)
+            }
+        };
+
+        let mut decoded = None;
+        for (idx, candidate) in self.candidates.iter_mut().enumerate() {
+            let llr = match candidate.phase.rotated {
+                false => llr,
+                true => match rotated_llr {
+                    Some(llr) => llr,
+                    None => continue,
+                },
+            };
+            if let Some(bit) = candidate.tick(&self.positions, llr) {
+                if idx == self.active {
+                    decoded = Some(S::from_llr(if bit { 1.0 } else { -1.0 }));
+                }
+            }
+        }
+
+        let (best, best_quality) = self
+            .candidates
+            .iter()
+            .map(|candidate| candidate.viterbi.quality())
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        if best_quality > self.candidates[self.active].viterbi.quality() + self.hysteresis {
+            self.active = best;
+        }
+        decoded
+    }
+
+    pub fn reset(&mut self) {
+        for candidate in self.candidates.iter_mut() {
+            candidate.skipped = 0;
+            candidate.pos = 0;
+            candidate.llrs = [0.0; 2];
+            candidate.viterbi.reset();
+        }
+        self.active = 0;
+        self.pending = None;
+        self.deferred = None;
+    }
+}
+
+impl PhaseCandidate {
+    /// Depunctures the received bits and runs a trellis step per input bit
+    fn tick(&mut self, positions: &[(usize, usize)], llr: f32) -> Option<bool> {
+        if self.skipped < self.phase.offset {
+            self.skipped += 1;
+            return None;
+        }
+        let (input, output) = positions[self.pos];
+        self.llrs[output] = llr;
+        self.pos = (self.pos + 1) % positions.len();
+        if positions[self.pos].0 == input && self.pos != 0 {
+            return None;
+        }
+        let decoded = self.viterbi.step(&self.llrs);
+        self.llrs = [0.0; 2];
+        decoded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CodePhase, ConvolutionalCode, ConvolutionalDecoder, ConvolutionalEncoder, Puncturing,
+        ViterbiDecoder,
+    };
+    use crate::test_utils::TestRng;
+
+    fn encode(code: &ConvolutionalCode, puncturing: &Puncturing, bits: &[bool]) -> Vec<bool> {
+        let mut encoder = ConvolutionalEncoder::new(code.clone(), puncturing.clone());
+        let mut coded = Vec::new();
+        for bit in bits {
+            encoder.tick(*bit, &mut coded);
+        }
+        coded
+    }
+
+    /// Bit errors of the last `tail` decoded bits, at their best alignment
+    /// to the input as bits may be lost while acquiring the code phase
+    fn tail_errors(bits: &[bool], decoded: &[bool], tail: usize) -> usize {
+        let decoded = &decoded[decoded.len() - tail..];
+        (tail..=bits.len())
+            .map(|end| {
+                bits[end - tail..end]
+                    .iter()
+                    .zip(decoded)
+                    .filter(|(bit, decoded)| bit != decoded)
+                    .count()
+            })
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_viterbi_corrects_noisy_symbols() {
+        let code = ConvolutionalCode::ccsds();
+        let mut rng = TestRng::new(43);
+        let bits: Vec<bool> = (0..4000).map(|_| rng.next_bool()).collect();
+        let coded = encode(&code, &Puncturing::none(), &bits);
+        // Eb/N0 of about 3 dB, i.e. ~8% raw symbol errors
+        let received: Vec<f32> = coded
+            .iter()
+            .map(|bit| if *bit { 1.0 } else { -1.0 } + 0.7 * rng.gaussian())
+            .collect();
+        let raw_errors = received
+            .iter()
+            .zip(coded.iter())
+            .filter(|(llr, bit)| (**llr >= 0.0) != **bit)
+            .count();
+        assert!(raw_errors > 200);
+
+        let mut viterbi = ViterbiDecoder::new(&code, 35);
+        let decoded: Vec<bool> = received
+            .chunks(2)
+            .filter_map(|llrs| viterbi.step(llrs))
+            .collect();
+        assert!(bits.len() - decoded.len() < 40);
+        assert!(tail_errors(&bits, &decoded, decoded.len()) <= 4);
+        assert!(viterbi.quality() > 0.5);
+    }
+
+    #[test]
+    fn test_punctured_rates_with_unknown_code_phase() {
+        let code = ConvolutionalCode::ccsds();
+        let mut rng = TestRng::new(44);
+        for (rate, junk) in [("1/2", 1), ("2/3", 2), ("3/4", 3), ("5/6", 5), ("7/8", 6)] {
+            let puncturing = Puncturing::parse(rate).unwrap();
+            let bits: Vec<bool> = (0..3000).map(|_| rng.next_bool()).collect();
+            let mut received: Vec<f32> = (0..junk).map(|_| rng.gaussian()).collect();
+            received.extend(
+                encode(&code, &puncturing, &bits)
+                    .iter()
+                    .map(|bit| if *bit { 1.0 } else { -1.0 } + 0.3 * rng.gaussian()),
+            );
+
+            let mut decoder = ConvolutionalDecoder::new(&code, &puncturing, false);
+            let decoded: Vec<bool> = received
+                .iter()
+                .filter_map(|llr| decoder.tick(*llr))
+                .map(|llr| llr >= 0.0)
+                .collect();
+            let phase = CodePhase {
+                offset: junk % puncturing.transmitted(),
+                rotated: false,
+            };
+            assert_eq!(decoder.code_phase(), phase, "rate {}", rate);
+            assert_eq!(tail_errors(&bits, &decoded, 1000), 0, "rate {}", rate);
+        }
+    }
+
+    #[test]
+    fn test_qpsk_rotation_is_resolved() {
+        let code = ConvolutionalCode::nasa();
+        let mut rng = TestRng::new(45);
+        for rate in ["1/2", "3/4"] {
+            let puncturing = Puncturing::parse(rate).unwrap();
+            let bits: Vec<bool> = (0..3000).map(|_| rng.next_bool()).collect();
+            let mut coded = encode(&code, &puncturing, &bits);
+            for pair in coded.chunks_exact_mut(2) {
+                let (a, b) = (pair[0], pair[1]);
+                pair.copy_from_slice(&[!b, a]);
+            }
+
+            let mut decoder = ConvolutionalDecoder::new(&code, &puncturing, true);
+            let decoded: Vec<bool> = coded.iter().filter_map(|bit| decoder.tick(*bit)).collect();
+            let phase = CodePhase {
+                offset: 0,
+                rotated: true,
+            };
+            assert_eq!(decoder.code_phase(), phase, "rate {}", rate);
+            assert_eq!(tail_errors(&bits, &decoded, 1000), 0, "rate {}", rate);
+        }
+    }
+}
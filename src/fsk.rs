@@ -0,0 +1,174 @@
+use std::f32::consts::PI;
+
+use num::complex::Complex32;
+
+/// Frequency discriminator, returns the phase advance between
+/// consecutive samples multiplied by `gain`.
+#[derive(Debug)]
+pub struct QuadratureDemod {
+    last: Complex32,
+    gain: f32,
+}
+
+impl QuadratureDemod {
+    pub fn new(gain: f32) -> Self {
+        Self {
+            last: Complex32::default(),
+            gain,
+        }
+    }
+
+    pub fn tick(&mut self, sample: Complex32) -> f32 {
+        let diff = sample * self.last.conj();
+        self.last = sample;
+        diff.arg() * self.gain
+    }
+}
+
+/// Gaussian lowpass as used for GMSK/GFSK pulse shaping, spanning
+/// `span` symbols at `sps` samples per symbol, with unity DC-gain.
+pub fn gaussian_taps(bt: f32, sps: f32, span: usize) -> Vec<f32> {
+    let n_taps = (span as f32 * sps).round() as usize | 1;
+    let center = (n_taps - 1) as f32 / 2.0;
+    let sigma = 2f32.ln().sqrt() / (2.0 * PI * bt);
+
+    let mut taps: Vec<f32> = (0..n_taps)
+        .map(|idx| {
+            let t = (idx as f32 - center) / sps;
+            (-t * t / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let gain: f32 = taps.iter().sum();
+    for tap in taps.iter_mut() {
+        *tap /= gain;
+    }
+    taps
+}
+
+/// Non-coherent (G)FSK demodulator: discriminator followed by a matched filter.
+///
+/// The output is normalized to +-1.0 for the two tones (positive
+/// frequency deviation being a `true` bit) and runs at the input rate,
+/// so it can be fed to `SymbolSync` as a real valued signal.
+#[derive(Debug)]
+pub struct FSKDemodulator {
+    discriminator: QuadratureDemod,
+    taps: Vec<f32>,
+    history: Vec<f32>,
+    history_idx: usize,
+}
+
+impl FSKDemodulator {
+    /// `mod_index` is the peak-to-peak frequency deviation in multiples of the symbol rate
+    pub fn new(sps: f32, mod_index: f32, taps: Vec<f32>) -> Self {
+        assert!(!taps.is_empty());
+        Self {
+            discriminator: QuadratureDemod::new(sps / (PI * mod_index)),
+            history: vec![0.0; taps.len()],
+            taps,
+            history_idx: 0,
+        }
+    }
+
+    /// GMSK / GFSK with a Gaussian matched filter
+    pub fn gaussian(sps: f32, mod_index: f32, bt: f32) -> Self {
+        Self::new(sps, mod_index, gaussian_taps(bt, sps, 3))
+    }
+
+    /// Plain 2-FSK, integrating over one symbol
+    pub fn rectangular(sps: f32, mod_index: f32) -> Self {
+        let n_taps = sps.round().max(1.0) as usize;
+        Self::new(sps, mod_index, vec![1.0 / n_taps as f32; n_taps])
+    }
+
+    pub fn tick(&mut self, sample: Complex32) -> f32 {
+        let n_taps = self.taps.len();
+        self.history_idx = (self.history_idx + 1) % n_taps;
+        self.history[self.history_idx] = self.discriminator.tick(sample);
+
+        let mut acc = 0.0;
+        for (idx, tap) in self.taps.iter().enumerate() {
+            acc += tap * self.history[(self.history_idx + n_taps - idx) % n_taps];
+        }
+        acc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gaussian_taps, FSKDemodulator};
+    use crate::test_utils::TestRng;
+    use crate::{GardnerErrorEstimator, SymbolSync};
+    use num::complex::Complex32;
+    use std::f32::consts::PI;
+
+    fn modulate(bits: &[bool], sps: usize, mod_index: f32, shaping: &[f32]) -> Vec<Complex32> {
+        let nrz: Vec<f32> = bits
+            .iter()
+            .flat_map(|bit| std::iter::repeat_n(if *bit { 1.0 } else { -1.0 }, sps))
+            .collect();
+
+        let mut phase = 0.0;
+        let mut rng = TestRng::new(3);
+        (0..nrz.len())
+            .map(|n| {
+                let freq: f32 = shaping
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, tap)| tap * nrz[(n + nrz.len() - idx) % nrz.len()])
+                    .sum();
+                phase += PI * mod_index * freq / sps as f32;
+                Complex32::cis(phase) + Complex32::new(rng.gaussian(), rng.gaussian()) * 0.1
+            })
+            .collect()
+    }
+
+    fn demodulate(demod: &mut FSKDemodulator, samples: &[Complex32], sps: usize) -> Vec<bool> {
+        let mut symbol_sync = SymbolSync::new(sps as f32, GardnerErrorEstimator {}, 0.01, 1.0);
+        samples
+            .iter()
+            .filter_map(|sample| symbol_sync.tick(Complex32::new(demod.tick(*sample), 0.0)))
+            .map(|symbol| symbol.re >= 0.0)
+            .collect()
+    }
+
+    fn assert_contains(decoded: &[bool], bits: &[bool]) {
+        let pattern = &bits[100..164];
+        assert!(
+            decoded
+                .windows(pattern.len())
+                .any(|window| window == pattern),
+            "transmitted bits not found in demodulated stream"
+        );
+    }
+
+    #[test]
+    fn test_gmsk_demodulation() {
+        const SPS: usize = 8;
+        let mut rng = TestRng::new(11);
+        let bits: Vec<bool> = (0..400).map(|_| rng.next_bool()).collect();
+        let samples = modulate(&bits, SPS, 0.5, &gaussian_taps(0.5, SPS as f32, 3));
+
+        let decoded = demodulate(
+            &mut FSKDemodulator::gaussian(SPS as f32, 0.5, 0.5),
+            &samples,
+            SPS,
+        );
+        assert_contains(&decoded, &bits);
+    }
+
+    #[test]
+    fn test_2fsk_demodulation() {
+        const SPS: usize = 10;
+        let mut rng = TestRng::new(12);
+        let bits: Vec<bool> = (0..400).map(|_| rng.next_bool()).collect();
+        let samples = modulate(&bits, SPS, 1.0, &[1.0]);
+
+        let decoded = demodulate(
+            &mut FSKDemodulator::rectangular(SPS as f32, 1.0),
+            &samples,
+            SPS,
+        );
+        assert_contains(&decoded, &bits);
+    }
+}
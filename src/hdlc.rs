@@ -0,0 +1,309 @@
+use std::fmt::Display;
+
+use crate::crc::Crc;
+
+#[cfg(test)]
+const FLAG: u8 = 0x7E;
+const FCS_LEN: usize = 2;
+pub const DEFAULT_MAX_FRAME_LEN: usize = 512;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HdlcStats {
+    pub frames_ok: usize,
+    pub fcs_errors: usize,
+    /// Seven or more consecutive ones inside a frame
+    pub aborts: usize,
+    /// Frame length not a multiple of 8 bits
+    pub misaligned: usize,
+    pub too_short: usize,
+    pub too_long: usize,
+}
+
+impl Display for HdlcStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ok, {} FCS errors, {} aborted, {} misaligned, {} too short, {} too long",
+            self.frames_ok,
+            self.fcs_errors,
+            self.aborts,
+            self.misaligned,
+            self.too_short,
+            self.too_long
+        )
+    }
+}
+
+/// HDLC deframer, collects the bits between two 0x7E flags, removes the
+/// stuffed zeros and validates the trailing CRC-16/X.25 FCS.
+/// Bytes are transmitted LSB first.
+#[derive(Debug)]
+pub struct HdlcDeframer {
+    ones: u8,
+    in_frame: bool,
+    max_len: usize,
+    bits: Vec<bool>,
+    frame: Vec<u8>,
+    fcs: Crc,
+    stats: HdlcStats,
+}
+
+impl Default for HdlcDeframer {
+    fn default() -> Self {
+        Self::with_max_len(DEFAULT_MAX_FRAME_LEN)
+    }
+}
+
+impl HdlcDeframer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `max_len` limits the frame length in bytes, excluding the FCS
+    pub fn with_max_len(max_len: usize) -> Self {
+        Self {
+            ones: 0,
+            in_frame: false,
+            max_len,
+            bits: Vec::new(),
+            frame: Vec::new(),
+            fcs: Crc::x25(),
+            stats: HdlcStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> &HdlcStats {
+        &self.stats
+    }
+
+    /// Returns the frame contents without FCS once a valid frame has been received
+    pub fn tick(&mut self, bit: bool) -> Option<&[u8]> {
+        if bit {
+            // An idle line may send ones indefinitely
+            self.ones = self.ones.saturating_add(1);
+            if self.ones >= 7 {
+                // Abort sequence or idle line
+                // The six preceding ones have been collected, idle after a flag is no abort
+                if self.in_frame && self.bits.len() > 6 {
+                    self.stats.aborts += 1;
+                }
+                self.in_frame = false;
+                self.bits.clear();
+                return None;
+            }
+            self.push_bit(true);
+            return None;
+        }
+
+        let ones = self.ones;
+        self.ones = 0;
+        match ones {
+            6 => {
+                // Flag, its leading zero and six ones have already been collected
+                self.bits.truncate(self.bits.len().saturating_sub(7));
+                let was_in_frame = self.in_frame;
+                self.in_frame = true;
+                let valid = was_in_frame && self.validate_frame();
+                self.bits.clear();
+                valid.then_some(self.frame.as_slice())
+            }
+            // Stuffed zero
+            5 => None,
+            _ => {
+                self.push_bit(false);
+                None
+            }
+        }
+    }
+
+    fn push_bit(&mut self, bit: bool) {
+        if !self.in_frame {
+            return;
+        }
+        // Leave room for the closing flag's leading zero and six ones
+        if self.bits.len() >= (self.max_len + FCS_LEN) * 8 + 7 {
+            self.stats.too_long += 1;
+            self.in_frame = false;
+            self.bits.clear();
+            return;
+        }
+        self.bits.push(bit);
+    }
+
+    fn validate_frame(&mut self) -> bool {
+        if self.bits.is_empty() {
+            // Consecutive flags
+            return false;
+        }
+        if !self.bits.len().is_multiple_of(8) {
+            self.stats.misaligned += 1;
+            return false;
+        }
+        if self.bits.len() <= FCS_LEN * 8 {
+            self.stats.too_short += 1;
+            return false;
+        }
+
+        self.frame = pack_lsb_first(&self.bits);
+        if !self.fcs.check(&self.frame) {
+            self.stats.fcs_errors += 1;
+            return false;
+        }
+        self.frame.truncate(self.frame.len() - FCS_LEN);
+
+        self.stats.frames_ok += 1;
+        true
+    }
+}
+
+fn pack_lsb_first(bits: &[bool]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |acc, (idx, bit)| acc | ((*bit as u8) << idx))
+        })
+        .collect()
+}
+
+/// Appends the FCS to `frame` and frames it between `num_flags` leading and a
+/// single trailing flag, inserting a zero after every five consecutive ones.
+#[cfg(test)]
+pub fn hdlc_encode(frame: &[u8], num_flags: usize) -> Vec<bool> {
+    let flag_bits = (0..8).map(|idx| (FLAG >> idx) & 1 != 0);
+    let mut bits: Vec<bool> = (0..num_flags).flat_map(|_| flag_bits.clone()).collect();
+
+    let crc = Crc::x25();
+    let fcs = crc.to_bytes(crc.checksum(frame));
+    let mut ones = 0;
+    for byte in frame.iter().chain(fcs.iter()) {
+        for idx in 0..8 {
+            let bit = (byte >> idx) & 1 != 0;
+            bits.push(bit);
+            if !bit {
+                ones = 0;
+                continue;
+            }
+            ones += 1;
+            if ones == 5 {
+                bits.push(false);
+                ones = 0;
+            }
+        }
+    }
+
+    bits.extend(flag_bits);
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hdlc_encode, HdlcDeframer, HdlcStats};
+
+    fn deframe(deframer: &mut HdlcDeframer, bits: &[bool]) -> Vec<Vec<u8>> {
+        bits.iter()
+            .filter_map(|bit| deframer.tick(*bit).map(|frame| frame.to_vec()))
+            .collect()
+    }
+
+    /// The FCS goes on the wire low byte first, and like every byte LSB first
+    #[test]
+    fn test_fcs_byte_order() {
+        let wire = |bytes: &[u8]| -> Vec<bool> {
+            [0x7E]
+                .iter()
+                .chain(bytes)
+                .chain([0x7E].iter())
+                .flat_map(|byte| (0..8).map(move |idx| (byte >> idx) & 1 != 0))
+                .collect()
+        };
+        // The CRC-16/X.25 of "123456789" is 0x906E, no zeros need to be stuffed
+        let bits = wire(b"123456789\x6E\x90");
+        assert_eq!(hdlc_encode(b"123456789", 1), bits);
+        let mut deframer = HdlcDeframer::new();
+        assert_eq!(deframe(&mut deframer, &bits), vec![b"123456789".to_vec()]);
+
+        let swapped = wire(b"123456789\x90\x6E");
+        assert!(deframe(&mut deframer, &swapped).is_empty());
+        assert_eq!(deframer.stats().fcs_errors, 1);
+    }
+
+    #[test]
+    fn test_hdlc_roundtrip_with_stuffing() {
+        let frame = [0x7E, 0xFF, 0x00, 0x1F, 0xF8, 0x41];
+        let bits = hdlc_encode(&frame, 3);
+        // 0x7E and 0xFF need stuffed zeros
+        assert!(bits.len() > 4 * 8 + (frame.len() + 2) * 8);
+
+        let mut deframer = HdlcDeframer::new();
+        assert_eq!(deframe(&mut deframer, &bits), vec![frame.to_vec()]);
+        assert_eq!(deframer.stats().frames_ok, 1);
+    }
+
+    #[test]
+    fn test_hdlc_shared_flags_and_abort() {
+        let mut bits = hdlc_encode(&[0x12, 0x34], 1);
+        bits.extend(hdlc_encode(&[0x56], 0));
+        // Aborted frame
+        bits.extend(hdlc_encode(&[0x78], 0).iter().take(12));
+        bits.extend([true; 8]);
+        bits.extend(hdlc_encode(&[0x9A, 0xBC], 1));
+
+        let mut deframer = HdlcDeframer::new();
+        assert_eq!(
+            deframe(&mut deframer, &bits),
+            vec![vec![0x12, 0x34], vec![0x56], vec![0x9A, 0xBC]]
+        );
+        assert_eq!(deframer.stats().aborts, 1);
+    }
+
+    #[test]
+    fn test_hdlc_long_idle_ones() {
+        let mut bits = hdlc_encode(&[0x12], 1);
+        bits.extend([true; 1000]);
+        bits.extend(hdlc_encode(&[0x34], 1));
+        // Aborted by the idle line
+        bits.extend(hdlc_encode(&[0x56], 1).iter().take(16));
+        bits.extend([true; 1000]);
+        bits.extend(hdlc_encode(&[0x78], 1));
+
+        let mut deframer = HdlcDeframer::new();
+        assert_eq!(
+            deframe(&mut deframer, &bits),
+            vec![vec![0x12], vec![0x34], vec![0x78]]
+        );
+        assert_eq!(deframer.stats().aborts, 1);
+    }
+
+    #[test]
+    fn test_hdlc_invalid_frames_are_counted() {
+        let mut deframer = HdlcDeframer::with_max_len(8);
+
+        let mut corrupted = hdlc_encode(b"hello", 1);
+        corrupted[20] ^= true;
+        assert!(deframe(&mut deframer, &corrupted).is_empty());
+
+        let too_long = hdlc_encode(b"much too long", 1);
+        assert!(deframe(&mut deframer, &too_long).is_empty());
+
+        // Single byte between flags
+        let flag: Vec<bool> = hdlc_encode(&[], 1).into_iter().take(8).collect();
+        let mut short = flag.clone();
+        short.extend([false, true, false, false, false, false, false, false]);
+        short.extend(flag);
+        assert!(deframe(&mut deframer, &short).is_empty());
+
+        assert_eq!(
+            *deframer.stats(),
+            HdlcStats {
+                frames_ok: 0,
+                fcs_errors: 1,
+                aborts: 0,
+                misaligned: 0,
+                too_short: 1,
+                too_long: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use num::complex::Complex32;
+
+use crate::PT1;
+
+/// Single pole IIR highpass removing a DC offset:
+/// `y[n] = x[n] - x[n-1] + pole * y[n-1]`
+#[derive(Debug)]
+pub struct DCBlocker {
+    pole: f32,
+    last_in: Complex32,
+    last_out: Complex32,
+}
+
+impl DCBlocker {
+    /// `pole` slightly below 1.0, the closer the narrower the notch
+    pub fn new(pole: f32) -> Self {
+        Self {
+            pole,
+            last_in: Complex32::default(),
+            last_out: Complex32::default(),
+        }
+    }
+
+    pub fn tick(&mut self, value: Complex32) -> Complex32 {
+        let out = value - self.last_in + self.last_out * self.pole;
+        self.last_in = value;
+        self.last_out = out;
+        out
+    }
+}
+
+/// Blind IQ gain and phase imbalance correction.
+///
+/// Models the input as `I = a cos(wt)`, `Q = g a sin(wt + phi)` and estimates
+/// `g` and `phi` from the smoothed powers and cross-correlation of I and Q.
+/// This assumes a circular input, i.e. I and Q of equal power and uncorrelated,
+/// which holds for noise and for signals with a residual frequency offset.
+#[derive(Debug)]
+pub struct IQImbalanceCorrector {
+    power_i: PT1<f32>,
+    power_q: PT1<f32>,
+    cross_iq: PT1<f32>,
+}
+
+impl IQImbalanceCorrector {
+    pub fn from_alpha(alpha: f32) -> Self {
+        Self {
+            power_i: PT1 {
+                alpha,
+                current: 1.0,
+            },
+            power_q: PT1 {
+                alpha,
+                current: 1.0,
+            },
+            cross_iq: PT1::from_alpha(alpha),
+        }
+    }
+
+    pub fn tick(&mut self, value: Complex32) -> Complex32 {
+        let power_i = self.power_i.tick(value.re * value.re);
+        let power_q = self.power_q.tick(value.im * value.im);
+        let cross_iq = self.cross_iq.tick(value.re * value.im);
+
+        if power_i <= 0.0 || power_q <= 0.0 {
+            return value;
+        }
+
+        let gain = (power_q / power_i).sqrt();
+        let sin_phi = (cross_iq / (power_i * power_q).sqrt()).clamp(-0.99, 0.99);
+        let cos_phi = (1.0 - sin_phi * sin_phi).sqrt();
+
+        let im = (value.im / gain - value.re * sin_phi) / cos_phi;
+        Complex32::new(value.re, im)
+    }
+
+    /// Estimated Q/I amplitude ratio
+    #[cfg(test)]
+    pub fn gain_imbalance(&self) -> f32 {
+        (self.power_q.current / self.power_i.current).sqrt()
+    }
+
+    /// Estimated phase imbalance in radians
+    #[cfg(test)]
+    pub fn phase_imbalance(&self) -> f32 {
+        let power = (self.power_i.current * self.power_q.current).sqrt();
+        (self.cross_iq.current / power).clamp(-1.0, 1.0).asin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DCBlocker, IQImbalanceCorrector};
+    use num::complex::Complex32;
+    use std::f32::consts::PI;
+
+    fn imbalanced_tone(n: usize, omega: f32, gain: f32, phase: f32) -> Complex32 {
+        let t = omega * n as f32;
+        Complex32::new(t.cos(), gain * (t + phase).sin())
+    }
+
+    /// Power at -omega relative to +omega over the given samples
+    fn image_rejection_db(samples: &[Complex32], omega: f32) -> f32 {
+        let mut wanted = Complex32::default();
+        let mut image = Complex32::default();
+        for (n, sample) in samples.iter().enumerate() {
+            wanted += sample * Complex32::cis(-omega * n as f32);
+            image += sample * Complex32::cis(omega * n as f32);
+        }
+        10.0 * (image.norm_sqr() / wanted.norm_sqr()).log10()
+    }
+
+    #[test]
+    fn test_dc_blocker() {
+        let mut blocker = DCBlocker::new(0.995);
+        let omega = 2.0 * PI / 20.0;
+        let offset = Complex32::new(0.3, -0.2);
+
+        let mut out = Vec::new();
+        for n in 0..10000 {
+            out.push(blocker.tick(Complex32::cis(omega * n as f32) + offset));
+        }
+
+        let settled = &out[5000..];
+        let mean = settled.iter().sum::<Complex32>() / settled.len() as f32;
+        assert!(mean.norm() < 1e-3, "mean: {}", mean);
+        assert!(settled.iter().all(|val| (val.norm() - 1.0).abs() < 0.02));
+    }
+
+    #[test]
+    fn test_iq_imbalance_correction() {
+        const GAIN: f32 = 1.2;
+        const PHASE: f32 = 10.0 * PI / 180.0;
+        let omega = 2.0 * PI / 37.0;
+        let mut corrector = IQImbalanceCorrector::from_alpha(0.001);
+
+        let input: Vec<_> = (0..20000)
+            .map(|n| imbalanced_tone(n, omega, GAIN, PHASE))
+            .collect();
+        let output: Vec<_> = input.iter().map(|val| corrector.tick(*val)).collect();
+
+        assert!((corrector.gain_imbalance() - GAIN).abs() < 0.01);
+        assert!((corrector.phase_imbalance() - PHASE).abs() < 0.01);
+
+        let rejection_before = image_rejection_db(&input[15000..], omega);
+        let rejection_after = image_rejection_db(&output[15000..], omega);
+        assert!(rejection_before > -25.0);
+        assert!(rejection_after < -40.0, "image: {} dB", rejection_after);
+    }
+}
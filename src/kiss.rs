@@ -0,0 +1,182 @@
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+
+const FEND: u8 = 0xC0;
+const FESC: u8 = 0xDB;
+const TFEND: u8 = 0xDC;
+const TFESC: u8 = 0xDD;
+const CMD_DATA: u8 = 0x00;
+/// The port is the upper nibble of the command byte
+const MAX_PORT: u8 = 15;
+
+/// Wraps `frame` into a KISS data frame for the given TNC port, `None` for ports above 15
+pub fn kiss_encode(port: u8, frame: &[u8]) -> Option<Vec<u8>> {
+    if port > MAX_PORT {
+        return None;
+    }
+    let mut encoded = Vec::with_capacity(frame.len() + 4);
+    encoded.push(FEND);
+    encoded.push((port << 4) | CMD_DATA);
+    for byte in frame {
+        match *byte {
+            FEND => encoded.extend([FESC, TFEND]),
+            FESC => encoded.extend([FESC, TFESC]),
+            byte => encoded.push(byte),
+        }
+    }
+    encoded.push(FEND);
+    Some(encoded)
+}
+
+/// Splits a KISS byte stream into (port, frame) tuples, skipping non-data commands
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct KissDecoder {
+    buffer: Vec<u8>,
+    escaped: bool,
+}
+
+#[cfg(test)]
+impl KissDecoder {
+    pub fn tick(&mut self, byte: u8) -> Option<(u8, Vec<u8>)> {
+        if byte == FEND {
+            self.escaped = false;
+            let frame = std::mem::take(&mut self.buffer);
+            let (command, data) = frame.split_first()?;
+            if command & 0x0F != CMD_DATA {
+                return None;
+            }
+            return Some((command >> 4, data.to_vec()));
+        }
+
+        let byte = match (self.escaped, byte) {
+            (false, FESC) => {
+                self.escaped = true;
+                return None;
+            }
+            (true, TFEND) => FEND,
+            (true, TFESC) => FESC,
+            (_, byte) => byte,
+        };
+        self.escaped = false;
+        self.buffer.push(byte);
+        None
+    }
+}
+
+/// KISS-over-TCP sink, sends every decoded frame to all connected clients.
+/// Clients are nonblocking, one that does not keep up with the frames is disconnected
+/// instead of stalling the decoder.
+#[derive(Debug)]
+pub struct KissServer {
+    listener: TcpListener,
+    clients: Vec<TcpStream>,
+}
+
+impl KissServer {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            clients: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts all pending client connections
+    pub fn poll(&mut self) {
+        while let Ok((client, _)) = self.listener.accept() {
+            if client.set_nonblocking(true).is_ok() {
+                let _ = client.set_nodelay(true);
+                self.clients.push(client);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Sends `frame` to all clients, clients that are disconnected or whose send buffer
+    /// is full (`WouldBlock`) are dropped. `None` for an invalid port.
+    pub fn send_frame(&mut self, port: u8, frame: &[u8]) -> Option<()> {
+        self.poll();
+        let encoded = kiss_encode(port, frame)?;
+        self.clients
+            .retain_mut(|client| client.write_all(&encoded).is_ok());
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{kiss_encode, KissDecoder, KissServer};
+    use std::io::Read;
+    use std::net::TcpStream;
+    use std::time::Duration;
+
+    #[test]
+    fn test_kiss_escaping_roundtrip() {
+        let frame = [0x01, 0xC0, 0x02, 0xDB, 0xDC];
+        let encoded = kiss_encode(1, &frame).unwrap();
+        assert_eq!(
+            encoded,
+            vec![0xC0, 0x10, 0x01, 0xDB, 0xDC, 0x02, 0xDB, 0xDD, 0xDC, 0xC0]
+        );
+
+        let mut decoder = KissDecoder::default();
+        let decoded: Vec<_> = encoded.iter().filter_map(|b| decoder.tick(*b)).collect();
+        assert_eq!(decoded, vec![(1, frame.to_vec())]);
+
+        let encoded = kiss_encode(15, &frame).unwrap();
+        assert_eq!(encoded[1], 0xF0);
+        assert_eq!(kiss_encode(16, &frame), None);
+    }
+
+    #[test]
+    fn test_kiss_server_loopback() {
+        let mut server = KissServer::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        server.send_frame(0, b"first").unwrap();
+        server.send_frame(0, &[0xC0, 0xDB]).unwrap();
+        assert_eq!(server.send_frame(16, b"invalid port"), None);
+        assert_eq!(server.client_count(), 1);
+
+        let mut decoder = KissDecoder::default();
+        let mut frames = Vec::new();
+        let mut buf = [0u8; 64];
+        while frames.len() < 2 {
+            let len = client.read(&mut buf).unwrap();
+            assert!(len > 0, "connection closed");
+            frames.extend(buf[..len].iter().filter_map(|b| decoder.tick(*b)));
+        }
+        assert_eq!(frames, vec![(0, b"first".to_vec()), (0, vec![0xC0, 0xDB])]);
+    }
+
+    #[test]
+    fn test_kiss_server_drops_stalled_client() {
+        let mut server = KissServer::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        server.poll();
+        assert_eq!(server.client_count(), 1);
+
+        // The client never reads, sending fails once the socket buffers are full
+        let frame = vec![0x55; 1 << 16];
+        for _ in 0..10_000 {
+            server.send_frame(0, &frame).unwrap();
+            if server.client_count() == 0 {
+                return;
+            }
+        }
+        panic!("stalled client was not dropped");
+    }
+}
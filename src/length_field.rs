@@ -0,0 +1,299 @@
+use crate::soft_bit::SoftBit;
+use crate::syncword::{Packetizer, SyncwordDetector, SyncwordVariant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
+/// Position and interpretation of a length field in the frame header.
+///
+/// The frame length in bits, counted from the end of the syncword and including
+/// the header, is `raw * scale + offset`. It has to be a multiple of 8 bits.
+/// Bits are transmitted MSB first, a little endian field is read byte-wise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthField {
+    /// Offset of the field from the end of the syncword
+    pub bit_offset: usize,
+    /// 1..=32 bits, little endian fields have to be whole bytes
+    pub width: usize,
+    pub endianness: Endianness,
+    pub scale: i64,
+    pub offset: i64,
+    /// Longest accepted frame in bits
+    pub max_len: usize,
+}
+
+impl LengthField {
+    /// Parses `<bit offset>,<width>,<be|le>,<scale>,<offset>,<max length>`,
+    /// e.g. `32,16,be,8,56,65592` for the data length of a CCSDS space packet
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut fields = spec.split(',');
+        let field = Self {
+            bit_offset: fields.next()?.parse().ok()?,
+            width: fields.next()?.parse().ok()?,
+            endianness: match fields.next()? {
+                "be" => Endianness::Big,
+                "le" => Endianness::Little,
+                _ => return None,
+            },
+            scale: fields.next()?.parse().ok()?,
+            offset: fields.next()?.parse().ok()?,
+            max_len: fields.next()?.parse().ok()?,
+        };
+        if fields.next().is_some() || !field.is_valid() {
+            return None;
+        }
+        Some(field)
+    }
+
+    fn is_valid(&self) -> bool {
+        (1..=32).contains(&self.width)
+            && (self.endianness == Endianness::Big || self.width.is_multiple_of(8))
+    }
+
+    /// Number of header bits needed to read the field
+    pub fn header_len(&self) -> usize {
+        self.bit_offset + self.width
+    }
+
+    /// Field value from the hard decisions of the header
+    pub fn raw_value<S: SoftBit>(&self, header: &[S]) -> Option<u32> {
+        if !self.is_valid() {
+            return None;
+        }
+        let bits = header.get(self.bit_offset..self.header_len())?;
+        let msb_first = |bits: &[S]| {
+            bits.iter()
+                .fold(0u32, |acc, bit| (acc << 1) | bit.hard() as u32)
+        };
+        Some(match self.endianness {
+            Endianness::Big => msb_first(bits),
+            Endianness::Little => bits
+                .chunks(8)
+                .rev()
+                .fold(0u32, |acc, byte| (acc << 8) | msb_first(byte)),
+        })
+    }
+
+    /// Frame length in bits, `None` if it is shorter than the header, exceeds `max_len`
+    /// or is no whole number of bytes
+    pub fn frame_len<S: SoftBit>(&self, header: &[S]) -> Option<usize> {
+        let len = (self.raw_value(header)? as i64)
+            .checked_mul(self.scale)?
+            .checked_add(self.offset)?;
+        let len = usize::try_from(len).ok()?;
+        if len < self.header_len() || len > self.max_len || !len.is_multiple_of(8) {
+            return None;
+        }
+        Some(len)
+    }
+}
+
+/// Collects variable length frames after each syncword, the length is read
+/// from the header as described by a `LengthField`.
+/// Frames with an invalid length are dropped and the syncword search resumes.
+pub struct LengthFieldPacketizer<S, D> {
+    detector: D,
+    field: LengthField,
+    variant: Option<SyncwordVariant>,
+
+    packet_active: bool,
+    frame_len: Option<usize>,
+    corrected: usize,
+    packet_buffer: Vec<S>,
+    invalid_lengths: usize,
+}
+
+impl<S, D> LengthFieldPacketizer<S, D>
+where
+    S: SoftBit,
+    D: SyncwordDetector<S>,
+{
+    pub fn new(detector: D, field: LengthField) -> Self {
+        Self {
+            detector,
+            field,
+            variant: None,
+            packet_active: false,
+            frame_len: None,
+            corrected: 0,
+            packet_buffer: Vec::with_capacity(field.max_len),
+            invalid_lengths: 0,
+        }
+    }
+
+    /// Frames dropped because of a length out of range or not byte aligned
+    pub fn invalid_lengths(&self) -> usize {
+        self.invalid_lengths
+    }
+
+    pub fn tick(&mut self, symbol_in: S) -> Option<&mut [S]> {
+        if !self.packet_active {
+            if let Some(variant) = self.detector.detect(symbol_in) {
+                self.packet_active = true;
+                self.variant = Some(variant);
+                self.frame_len = None;
+                self.corrected = 0;
+                self.packet_buffer.clear();
+                self.detector.reset();
+            }
+            return None;
+        }
+
+        self.packet_buffer.push(symbol_in);
+        self.correct_phase();
+
+        let frame_len = match self.frame_len {
+            Some(frame_len) => frame_len,
+            None if self.corrected < self.field.header_len() => return None,
+            None => match self.field.frame_len(&self.packet_buffer) {
+                Some(frame_len) => *self.frame_len.insert(frame_len),
+                None => {
+                    self.invalid_lengths += 1;
+                    self.packet_active = false;
+                    return None;
+                }
+            },
+        };
+
+        if self.packet_buffer.len() < frame_len {
+            return None;
+        }
+        self.packet_active = false;
+        self.packet_buffer.truncate(frame_len);
+        Some(self.packet_buffer.as_mut_slice())
+    }
+
+    /// Undoes the phase rotation for all complete symbols received so far
+    fn correct_phase(&mut self) {
+        let len = self.packet_buffer.len();
+        match self.variant {
+            Some(variant) => {
+                let end = len - len % variant.bits_per_symbol();
+                variant.correct(&mut self.packet_buffer[self.corrected..end]);
+                self.corrected = end;
+            }
+            None => self.corrected = len,
+        }
+    }
+
+    pub fn matched_variant(&self) -> Option<SyncwordVariant> {
+        self.variant
+    }
+
+    pub fn reset(&mut self) {
+        self.packet_active = false;
+        self.variant = None;
+        self.packet_buffer.clear();
+        self.detector.reset();
+    }
+}
+
+impl<S, D> Packetizer<S> for LengthFieldPacketizer<S, D>
+where
+    S: SoftBit,
+    D: SyncwordDetector<S>,
+{
+    fn tick(&mut self, symbol_in: S) -> Option<&mut [S]> {
+        LengthFieldPacketizer::tick(self, symbol_in)
+    }
+
+    fn matched_variant(&self) -> Option<SyncwordVariant> {
+        LengthFieldPacketizer::matched_variant(self)
+    }
+
+    fn reset(&mut self) {
+        LengthFieldPacketizer::reset(self)
+    }
+
+    fn invalid_lengths(&self) -> Option<usize> {
+        Some(LengthFieldPacketizer::invalid_lengths(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Endianness, LengthField, LengthFieldPacketizer};
+    use crate::syncword::{syncword_bits, PackedSyncwordScan, PhaseAmbiguity};
+    use crate::test_utils::{bytes_to_bits, TestRng};
+
+    #[test]
+    fn test_length_field_parse_and_read() {
+        let field = LengthField::parse("8,16,be,8,24,1024").unwrap();
+        let header = bytes_to_bits(&[0xAA, 0x00, 0x05]);
+        assert_eq!(field.raw_value(&header), Some(5));
+        assert_eq!(field.frame_len(&header), Some(5 * 8 + 24));
+        assert_eq!(field.frame_len(&header[..20]), None);
+
+        let field = LengthField {
+            endianness: Endianness::Little,
+            ..field
+        };
+        assert_eq!(field.raw_value(&header), Some(0x0500));
+        // Exceeds max_len
+        assert_eq!(field.frame_len(&header), None);
+
+        // Lengths that are no whole number of bytes or overflow are rejected
+        let field = LengthField::parse("8,16,be,1,24,1024").unwrap();
+        assert_eq!(field.frame_len(&header), None);
+        assert_eq!(
+            field.frame_len(&bytes_to_bits(&[0xAA, 0x00, 0x08])),
+            Some(32)
+        );
+        let field = LengthField {
+            scale: i64::MAX,
+            ..field
+        };
+        assert_eq!(field.frame_len(&header), None);
+        let field = LengthField {
+            scale: -8,
+            max_len: usize::MAX,
+            ..field
+        };
+        assert_eq!(field.frame_len(&header), None);
+
+        assert_eq!(LengthField::parse("4,12,le,1,0,100"), None);
+        assert_eq!(LengthField::parse("0,33,be,1,0,100"), None);
+        assert_eq!(LengthField::parse("0,8,be,1,0"), None);
+    }
+
+    #[test]
+    fn test_variable_length_frames() {
+        const SYNC: u64 = 0x1ACFFC1D;
+        // Header: one byte frame id and a byte counting the payload bytes
+        let field = LengthField::parse("8,8,be,8,16,1024").unwrap();
+        let mut rng = TestRng::new(40);
+
+        let frame = |id: u8, len: u8| {
+            let mut bytes = vec![id, len];
+            bytes.extend((0..len).map(|idx| idx ^ id));
+            bytes_to_bits(&bytes)
+        };
+        let mut bits: Vec<bool> = (0..50).map(|_| rng.next_bool()).collect();
+        let mut expected = Vec::new();
+        for (id, len) in [(1, 3), (2, 0), (3, 200), (4, 17)] {
+            bits.extend(syncword_bits::<32>(SYNC));
+            let frame = frame(id, len);
+            bits.extend(frame.iter());
+            // Frame 3 exceeds the maximum length
+            if id != 3 {
+                expected.push(frame);
+            }
+        }
+        bits.extend((0..50).map(|_| rng.next_bool()));
+        // 90° phase offset
+        PhaseAmbiguity::Qpsk.variants()[1].apply(&mut bits);
+
+        let detector = PackedSyncwordScan::new(SYNC, 32, 0, PhaseAmbiguity::Qpsk);
+        let mut packetizer = LengthFieldPacketizer::new(detector, field);
+        let frames: Vec<Vec<bool>> = bits
+            .iter()
+            .filter_map(|bit| packetizer.tick(*bit).map(|frame| frame.to_vec()))
+            .collect();
+        assert_eq!(frames, expected);
+        assert_eq!(packetizer.invalid_lengths(), 1);
+        assert_eq!(packetizer.matched_variant().unwrap().rotation_deg, 90);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::soft_bit::SoftBit;
+
+/// NRZ line codes, given by what the line level represents:
+/// - `NrzL`: the level is the bit
+/// - `NrzM`: a level change is a `true` bit (differential BPSK)
+/// - `NrzS`: a level change is a `false` bit (NRZI as used by AX.25)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineCode {
+    NrzL,
+    NrzM,
+    NrzS,
+}
+
+impl LineCode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "nrz-l" => Some(Self::NrzL),
+            "nrz-m" => Some(Self::NrzM),
+            "nrz-s" | "nrzi" => Some(Self::NrzS),
+            _ => None,
+        }
+    }
+}
+
+/// Converts line levels (hard or soft) back to NRZ-L bits.
+/// NRZ-M and NRZ-S are insensitive to an inverted line polarity.
+#[derive(Debug)]
+pub struct LineDecoder<S> {
+    code: LineCode,
+    last_level: S,
+}
+
+impl<S: SoftBit> LineDecoder<S> {
+    pub fn new(code: LineCode) -> Self {
+        Self {
+            code,
+            last_level: S::default(),
+        }
+    }
+
+    pub fn tick(&mut self, level: S) -> S {
+        let transition = level.xor(self.last_level);
+        self.last_level = level;
+        match self.code {
+            LineCode::NrzL => level,
+            LineCode::NrzM => transition,
+            LineCode::NrzS => transition.invert(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug)]
+pub struct LineEncoder {
+    code: LineCode,
+    level: bool,
+}
+
+#[cfg(test)]
+impl LineEncoder {
+    pub fn new(code: LineCode) -> Self {
+        Self { code, level: false }
+    }
+
+    pub fn tick(&mut self, bit: bool) -> bool {
+        match self.code {
+            LineCode::NrzL => self.level = bit,
+            LineCode::NrzM => self.level ^= bit,
+            LineCode::NrzS => self.level ^= !bit,
+        }
+        self.level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineCode, LineDecoder, LineEncoder};
+    use crate::test_utils::TestRng;
+
+    fn random_bits(n: usize) -> Vec<bool> {
+        let mut rng = TestRng::new(21);
+        (0..n).map(|_| rng.next_bool()).collect()
+    }
+
+    #[test]
+    fn test_line_code_roundtrip() {
+        let bits = random_bits(200);
+        for code in [LineCode::NrzL, LineCode::NrzM, LineCode::NrzS] {
+            let mut encoder = LineEncoder::new(code);
+            let mut decoder = LineDecoder::<bool>::new(code);
+            for bit in bits.iter() {
+                assert_eq!(decoder.tick(encoder.tick(*bit)), *bit, "{:?}", code);
+            }
+        }
+    }
+
+    #[test]
+    fn test_differential_codes_resolve_inversion() {
+        let bits = random_bits(200);
+        for code in [LineCode::NrzM, LineCode::NrzS] {
+            let mut encoder = LineEncoder::new(code);
+            let mut decoder = LineDecoder::<f32>::new(code);
+            for (idx, bit) in bits.iter().enumerate() {
+                // Inverted soft levels, as after a 180° phase flip
+                let level = if encoder.tick(*bit) { -0.8 } else { 1.3 };
+                let decoded = decoder.tick(level);
+                // The very first bit depends on the unknown previous level
+                if idx > 0 {
+                    assert_eq!(decoded >= 0.0, *bit, "{:?}", code);
+                    assert!(decoded.abs() >= 0.8);
+                }
+            }
+        }
+    }
+}
@@ -1,29 +1,69 @@
+#![allow(clippy::upper_case_acronyms)]
+
+use afsk::Bell202Demodulator;
+use agc::{db_to_gain, AGC, DEFAULT_MAX_GAIN_DB};
+use ax25::Ax25Frame;
 use bitstream_decoder::BitStreamDecoder;
+use ccsds::tm::{Ocf, TmDemultiplexer, TmFrame};
+use ccsds::{FrameQuality, FrameStats, RS_INTERLEAVE_DEPTH};
+use channelizer::PolyphaseChannelizer;
 use clock::SimpleClock;
+use constellation::{Constellation, DifferentialDetector, OQPSKAligner};
+use crc::{CrcStats, FrameCheck};
+use fec::ldpc::{parse_ar4ja_phi, Ar4jaRate, LdpcCode, LdpcDecoder, ParityCheckMatrix};
+use fec::reed_solomon::ReedSolomon;
+use fec::turbo::{TurboDecoder, TurboRate};
+use fec::viterbi::{ConvolutionalCode, ConvolutionalDecoder, Puncturing};
 use fir_interpolator_taps::{FIRInterpolator, Interpolateable};
-use num::complex::ComplexFloat;
+use fsk::{FSKDemodulator, QuadratureDemod};
+use hdlc::HdlcDeframer;
+use iq_correction::{DCBlocker, IQImbalanceCorrector};
+use kiss::KissServer;
+use length_field::{LengthField, LengthFieldPacketizer};
+use line_coding::{LineCode, LineDecoder};
 use num::Num;
 use num::{complex::Complex32, Complex};
+use soft_bit::{i8_llr_scale, quantize_llr, NoiseVarianceEstimator, SoftBit};
+use squelch::{Squelch, SquelchEvent};
 use std::env::args;
 use std::f32::consts::PI;
 use std::fmt::Debug;
 use std::io::{BufReader, Read, SeekFrom, Write};
 use std::ops::{Index, Mul};
 use std::{fs, io::Seek};
-use syncword::SyncwordPacketizer;
+use syncword::{
+    hex_bits, syncword_bits, OverlapPolicy, PackedSyncwordScan, Packetizer, PhaseAmbiguity,
+    SoftSyncwordCorrelator, SyncwordDetector, SyncwordPacketizer, SyncwordVariant,
+};
 
 use crate::bytes::Bytes;
 use crate::signals::lsfr::LSFR;
-use crate::syncword::SyncwordScanXCorr;
+use crate::signals::prbs::{BerEvent, BerTester, Prbs};
 
 // mod block_macro;
+mod afsk;
+mod agc;
+mod ax25;
 mod bitstream_decoder;
 mod bytes;
+mod ccsds;
+mod channelizer;
 mod clock;
+mod constellation;
+mod crc;
+mod fec;
 mod fir_interpolator_taps;
+mod fsk;
+mod hdlc;
+mod iq_correction;
+mod kiss;
+mod length_field;
+mod line_coding;
 mod pll;
 mod ringbuffer;
 mod signals;
+mod soft_bit;
+mod squelch;
 mod syncword;
 
 #[cfg(test)]
@@ -37,11 +77,16 @@ fn read_complex_value<T: Read>(source_stream: &mut T) -> Option<Complex<f32>> {
 
     source_stream.read_exact(&mut buf_im).ok()?;
     let im = f32::from_ne_bytes(buf_im);
-    return Some(Complex::new(re, im));
+    Some(Complex::new(re, im))
+}
+
+fn read_real_value<T: Read>(source_stream: &mut T) -> Option<f32> {
+    let mut buf = [0; 4];
+    source_stream.read_exact(&mut buf).ok()?;
+    Some(f32::from_ne_bytes(buf))
 }
 
-fn open_source_file() -> Option<fs::File> {
-    let filename = args().nth(1)?;
+fn open_source_file(filename: &str) -> Option<fs::File> {
     let mut file = fs::File::open(filename).ok()?;
 
     let file_len = file.seek(SeekFrom::End(0)).ok()?;
@@ -107,26 +152,9 @@ where
         self.integrator = self.integrator + self.ki * val;
         self.integrator + self.kp * val
     }
-}
-
-struct AGC {
-    pt: PT1<f32>,
-}
-
-impl AGC {
-    fn from_alpha(alpha: f32) -> Self {
-        Self {
-            pt: PT1 {
-                alpha,
-                current: 1.0,
-            },
-        }
-    }
 
-    fn tick(&mut self, value: Complex32) -> Complex32 {
-        let value_norm_sqr = value.norm_sqr();
-        let square_filtered = self.pt.tick(value_norm_sqr);
-        value / square_filtered.sqrt()
+    fn reset(&mut self) {
+        self.integrator = T::zero();
     }
 }
 
@@ -266,33 +294,833 @@ where
         }
         None
     }
+
+    /// Drops all sample history and timing state, e.g. at the start of a new burst
+    fn reset(&mut self) {
+        self.input_sample_buffer = Default::default();
+        self.interp_sample_buffer = Default::default();
+        self.interp_clock.set_phase(0.0);
+        self.timing_controller.reset();
+        self.output_sample = true;
+    }
 }
 
-const SYNCWORD: [bool; 32] = [
-    false, false, false, true, true, false, true, false, true, true, false, false, true, true,
-    true, true, true, true, true, true, true, true, false, false, false, false, false, true, true,
-    true, false, true,
-];
+const SYNCWORD_HEX: u64 = ccsds::ASM;
+const SYNCWORD: [bool; 32] = syncword_bits(SYNCWORD_HEX);
+/// Packet length in bits without FEC or length field
+const PACKET_LEN: usize = 10200;
 
-fn main() -> Result<(), ()> {
-    let mut source = BufReader::new(open_source_file().expect("Failed to open source-file!"));
-    let mut acg_filter = AGC::from_alpha(0.01);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Modulation {
+    BPSK,
+    QPSK,
+    PSK8,
+    OQPSK,
+    GMSK,
+    FSK2,
+}
+
+impl Modulation {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "bpsk" => Some(Self::BPSK),
+            "qpsk" => Some(Self::QPSK),
+            "8psk" => Some(Self::PSK8),
+            "oqpsk" => Some(Self::OQPSK),
+            "gmsk" => Some(Self::GMSK),
+            "2fsk" => Some(Self::FSK2),
+            _ => None,
+        }
+    }
+
+    fn constellation(&self) -> Constellation {
+        match self {
+            // FSK demodulates to a real valued +-1.0 signal
+            Self::BPSK | Self::GMSK | Self::FSK2 => Constellation::bpsk(),
+            Self::QPSK | Self::OQPSK => Constellation::qpsk(),
+            Self::PSK8 => Constellation::psk8(),
+        }
+    }
+
+    /// Carrier phase ambiguities resolved by the syncword search.
+    /// FSK has none, 8PSK is not supported.
+    fn phase_ambiguity(&self) -> PhaseAmbiguity {
+        match self {
+            Self::BPSK => PhaseAmbiguity::Bpsk,
+            Self::QPSK | Self::OQPSK => PhaseAmbiguity::Qpsk,
+            Self::PSK8 | Self::GMSK | Self::FSK2 => PhaseAmbiguity::None,
+        }
+    }
+
+    /// Half-symbol alignment, only needed for offset modulations
+    fn aligner(&self, sps: f32) -> Option<OQPSKAligner> {
+        match self {
+            Self::OQPSK => Some(OQPSKAligner::new(sps)),
+            _ => None,
+        }
+    }
+
+    /// Frequency discriminator, replacing the matched filter for FSK modulations
+    fn fsk_demodulator(&self, sps: f32, mod_index: f32) -> Option<FSKDemodulator> {
+        match self {
+            Self::GMSK => Some(FSKDemodulator::gaussian(sps, mod_index, 0.5)),
+            Self::FSK2 => Some(FSKDemodulator::rectangular(sps, mod_index)),
+            _ => None,
+        }
+    }
+}
+
+/// Per-channel AGC -> SymbolSync -> SyncwordPacketizer chain,
+/// fed by one output of the `PolyphaseChannelizer`
+struct ChannelChain {
+    channel: usize,
+    agc: AGC,
+    oqpsk_aligner: Option<OQPSKAligner>,
+    symbol_sync: SymbolSync<GardnerErrorEstimator>,
+    constellation: Constellation,
+    syncword_packetizer: SyncwordPacketizer<bool, PackedSyncwordScan>,
+}
+
+impl ChannelChain {
+    fn new(channel: usize, sps: f32, modulation: Modulation, max_gain: f32) -> Self {
+        Self {
+            channel,
+            agc: AGC::new(0.05, 0.01, 1.0, 0.0, max_gain),
+            oqpsk_aligner: modulation.aligner(sps),
+            symbol_sync: SymbolSync::new(sps, GardnerErrorEstimator {}, 0.0, 0.0),
+            constellation: modulation.constellation(),
+            syncword_packetizer: SyncwordPacketizer::new(
+                PackedSyncwordScan::new(SYNCWORD_HEX, 32, 1, modulation.phase_ambiguity()),
+                PACKET_LEN,
+            ),
+        }
+    }
+
+    /// Returns the hard decided symbol, once per symbol period
+    fn tick(&mut self, sample: Complex32) -> Option<u8> {
+        let mut agc_sample = self.agc.tick(sample);
+        if let Some(aligner) = self.oqpsk_aligner.as_mut() {
+            agc_sample = aligner.tick(agc_sample);
+        }
+        let symbol_out = self.symbol_sync.tick(agc_sample)?;
+        Some(self.constellation.decide(symbol_out))
+    }
+}
+
+/// DC and IQ imbalance correction, applied to the raw input samples before the AGC
+struct FrontEndCorrection {
+    dc_blocker: DCBlocker,
+    iq_corrector: IQImbalanceCorrector,
+}
+
+impl FrontEndCorrection {
+    fn new() -> Self {
+        Self {
+            dc_blocker: DCBlocker::new(0.999),
+            iq_corrector: IQImbalanceCorrector::from_alpha(1e-4),
+        }
+    }
+
+    fn tick(&mut self, sample: Complex32) -> Complex32 {
+        self.iq_corrector.tick(self.dc_blocker.tick(sample))
+    }
+}
+
+const SQUELCH_HYSTERESIS_DB: f32 = 3.0;
+
+struct Options {
+    filename: String,
+    sps: f32,
+    num_channels: Option<usize>,
+    selected_channels: Option<Vec<usize>>,
+    squelch_db: Option<f32>,
+    agc_max_gain_db: f32,
+    iq_correction: bool,
+    modulation: Modulation,
+    soft: bool,
+    /// Quantize the LLRs to `i8`
+    soft_i8: bool,
+    fsk_index: f32,
+    afsk: bool,
+    audio_input: bool,
+    sample_rate: f32,
+    kiss_addr: Option<String>,
+    line_code: LineCode,
+    differential: bool,
+    sync_threshold: Option<f32>,
+    length_field: Option<LengthField>,
+    overlap: OverlapPolicy,
+    rs_depth: Option<usize>,
+    viterbi: Option<(ConvolutionalCode, Puncturing)>,
+    ldpc: Option<LdpcCode>,
+    ldpc_iterations: usize,
+    /// Information block length and rate
+    turbo: Option<(usize, TurboRate)>,
+    turbo_iterations: usize,
+    crc: Option<FrameCheck>,
+    /// Measure the bit error rate on a PRBS instead of decoding packets
+    ber: Option<Prbs>,
+    /// Parse the frames as TM transfer frames, the flag tells whether they end with a FECF
+    tm: Option<bool>,
+}
+
+const USAGE: &str = "usage: rust-rf-decoder <iq-file> [--sps <samples/symbol>] \
+[--modulation <bpsk|qpsk|8psk|oqpsk|gmsk|2fsk>] \
+[--fsk-index <h>] [--soft | --soft-i8] \
+[--differential] [--line-code <nrz-l|nrz-m|nrz-s>] [--sync-threshold <sigma>] \
+[--length-field <offset,width,be|le,scale,offset,max>] \
+[--overlap <block|restart|parallel[:N]>] [--rs <interleave depth>] \
+[--viterbi <1/2|2/3|3/4|5/6|7/8>[,<g1>,<g2>]] [--preset ccsds] \
+[--ldpc <alist file|c2|ar4ja:<k>:<rate>:<phi file>>[,<punctured bits>[,<fill bits>,<appended bits>]] [--ldpc-iterations <N>]] \
+[--turbo <1784|3568|7136|8920>,<1/2|1/3|1/4|1/6> [--turbo-iterations <N>]] \
+[--crc <ccitt|x25|crc32|width:poly:init:refin:refout:xorout>[,<offset>][,drop]] \
+[--ber <prbs9|prbs15|prbs23|prbs31>] [--tm [--tm-fecf]] \
+[--iq-correction] [--squelch <dB>] [--agc-max-gain <dB>] \
+[--channels <N> [--select <c0,c1,...>]] \
+[--afsk [--audio] [--sample-rate <Hz>] [--kiss <addr:port>]]";
+
+impl Options {
+    /// `ccsds`: soft decision r=1/2 Viterbi decoding, ASM search on the decoded bits,
+    /// derandomization and RS(255,223) decoding with interleave depth 5
+    fn apply_preset(&mut self, preset: &str) -> Option<()> {
+        match preset {
+            "ccsds" => {
+                self.soft = true;
+                self.viterbi = Some((ConvolutionalCode::ccsds(), Puncturing::none()));
+                self.rs_depth = Some(RS_INTERLEAVE_DEPTH);
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+
+    /// Rejects option combinations the decoder does not implement
+    fn is_supported(&self) -> bool {
+        // The channelized chain slices PSK symbols to hard bits and searches the
+        // syncword with at most one bit error, without a line or channel code
+        let single_channel_only = matches!(self.modulation, Modulation::GMSK | Modulation::FSK2)
+            || self.soft
+            || self.squelch_db.is_some()
+            || self.differential
+            || self.line_code != LineCode::NrzL
+            || self.sync_threshold.is_some()
+            || self.length_field.is_some()
+            || self.viterbi.is_some()
+            || self.ldpc.is_some()
+            || self.turbo.is_some()
+            || self.ber.is_some();
+        if self.num_channels.is_some() && single_channel_only {
+            return false;
+        }
+        // Only existing channelizer outputs can be selected
+        match (&self.selected_channels, self.num_channels) {
+            (Some(_), None) => return false,
+            (Some(selected), Some(num_channels)) if selected.iter().any(|c| *c >= num_channels) => {
+                return false
+            }
+            _ => {}
+        }
+        // Length field frames are collected one at a time
+        if self.length_field.is_some() && self.overlap != OverlapPolicy::Blocking {
+            return false;
+        }
+        // Parallel candidates are told apart by the RS decoder or the CRC
+        let validated = self.rs_depth.is_some() || self.crc.is_some();
+        if matches!(self.overlap, OverlapPolicy::Parallel { .. }) && !validated {
+            return false;
+        }
+        // Codewords have a fixed length, and only one code can be decoded
+        let codes = self.ldpc.is_some() as usize + self.turbo.is_some() as usize;
+        if codes > 1 || (self.length_field.is_some() && codes > 0) {
+            return false;
+        }
+        true
+    }
+
+    /// Bits collected after the syncword
+    fn packet_len(&self) -> usize {
+        match (&self.ldpc, &self.turbo) {
+            (Some(code), _) => code.frame_len(),
+            (None, Some((k, rate))) => rate.codeword_len(*k),
+            (None, None) => PACKET_LEN,
+        }
+    }
+}
+
+/// Parity check matrix of `--ldpc` and its default punctured, fill and appended bits:
+/// `c2`, `ar4ja:<k>:<rate>:<phi file>` or an alist file
+fn ldpc_matrix(name: &str) -> Option<(ParityCheckMatrix, [usize; 3])> {
+    if name == "c2" {
+        return Some((ParityCheckMatrix::ccsds_c2(), [0, 18, 2]));
+    }
+    if let Some(spec) = name.strip_prefix("ar4ja:") {
+        let mut fields = spec.splitn(3, ':');
+        let k = fields.next()?.parse().ok()?;
+        let rate = Ar4jaRate::parse(fields.next()?)?;
+        let block_size = rate.block_size(k)?;
+        let phi = fs::read_to_string(fields.next()?).expect("Failed to read AR4JA phi file!");
+        let phi = parse_ar4ja_phi(&phi, block_size).expect("Invalid AR4JA phi file!");
+        let h = ParityCheckMatrix::ar4ja(rate, block_size, &phi);
+        return Some((h, [block_size, 0, 0]));
+    }
+    let alist = fs::read_to_string(name).expect("Failed to read alist file!");
+    let h = ParityCheckMatrix::from_alist(&alist).expect("Invalid alist file!");
+    Some((h, [0, 0, 0]))
+}
+
+fn parse_args() -> Option<Options> {
+    let mut args = args().skip(1);
+    let mut options = Options {
+        filename: args.next()?,
+        sps: 5.0,
+        num_channels: None,
+        selected_channels: None,
+        squelch_db: None,
+        agc_max_gain_db: DEFAULT_MAX_GAIN_DB,
+        iq_correction: false,
+        modulation: Modulation::BPSK,
+        soft: false,
+        soft_i8: false,
+        fsk_index: 0.5,
+        afsk: false,
+        audio_input: false,
+        sample_rate: 48000.0,
+        kiss_addr: None,
+        line_code: LineCode::NrzL,
+        differential: false,
+        sync_threshold: None,
+        length_field: None,
+        overlap: OverlapPolicy::Blocking,
+        rs_depth: None,
+        viterbi: None,
+        ldpc: None,
+        ldpc_iterations: 50,
+        turbo: None,
+        turbo_iterations: 10,
+        crc: None,
+        ber: None,
+        tm: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sps" => options.sps = args.next()?.parse().ok()?,
+            "--modulation" => options.modulation = Modulation::parse(&args.next()?)?,
+            "--fsk-index" => options.fsk_index = args.next()?.parse().ok()?,
+            "--afsk" => options.afsk = true,
+            "--audio" => options.audio_input = true,
+            "--sample-rate" => options.sample_rate = args.next()?.parse().ok()?,
+            "--kiss" => options.kiss_addr = Some(args.next()?),
+            "--soft" => options.soft = true,
+            "--soft-i8" => {
+                options.soft = true;
+                options.soft_i8 = true;
+            }
+            "--differential" => options.differential = true,
+            "--sync-threshold" => options.sync_threshold = Some(args.next()?.parse().ok()?),
+            "--length-field" => options.length_field = Some(LengthField::parse(&args.next()?)?),
+            "--overlap" => options.overlap = OverlapPolicy::parse(&args.next()?)?,
+            "--tm" => options.tm = Some(options.tm.unwrap_or(false)),
+            "--tm-fecf" => options.tm = Some(true),
+            "--ber" => options.ber = Some(Prbs::parse(&args.next()?)?),
+            "--crc" => options.crc = Some(FrameCheck::parse(&args.next()?)?),
+            "--rs" => {
+                let depth = args.next()?.parse().ok();
+                options.rs_depth = Some(depth.filter(|depth| (1..=8).contains(depth))?);
+            }
+            "--viterbi" => {
+                let spec = args.next()?;
+                let (rate, code) = match spec.split_once(',') {
+                    Some((rate, polys)) => (rate, ConvolutionalCode::parse(polys)?),
+                    None => (spec.as_str(), ConvolutionalCode::ccsds()),
+                };
+                options.viterbi = Some((code, Puncturing::parse(rate)?));
+            }
+            "--ldpc" => {
+                let spec = args.next()?;
+                let mut fields = spec.split(',');
+                let (h, defaults) = ldpc_matrix(fields.next()?)?;
+                let numbers: Vec<usize> = fields
+                    .map(|field| field.parse().ok())
+                    .collect::<Option<_>>()?;
+                // Explicit layouts replace the default one, missing numbers are zero
+                let [punctured, fill, appended] = match numbers.len() {
+                    0 => defaults,
+                    1..=3 => [0, 1, 2].map(|idx| numbers.get(idx).copied().unwrap_or(0)),
+                    _ => return None,
+                };
+                options.ldpc = Some(LdpcCode::new(h, fill, punctured, appended)?);
+            }
+            "--ldpc-iterations" => options.ldpc_iterations = args.next()?.parse().ok()?,
+            "--turbo" => {
+                let spec = args.next()?;
+                let (k, rate) = spec.split_once(',')?;
+                let k = k
+                    .parse()
+                    .ok()
+                    .filter(|k| [1784, 3568, 7136, 8920].contains(k))?;
+                options.turbo = Some((k, TurboRate::parse(rate)?));
+            }
+            "--turbo-iterations" => options.turbo_iterations = args.next()?.parse().ok()?,
+            "--preset" => options.apply_preset(&args.next()?)?,
+            "--line-code" => options.line_code = LineCode::parse(&args.next()?)?,
+            "--iq-correction" => options.iq_correction = true,
+            "--squelch" => options.squelch_db = Some(args.next()?.parse().ok()?),
+            "--agc-max-gain" => options.agc_max_gain_db = args.next()?.parse().ok()?,
+            "--channels" => options.num_channels = Some(args.next()?.parse().ok()?),
+            "--select" => {
+                let list = args.next()?;
+                let channels: Result<Vec<usize>, _> = list.split(',').map(str::parse).collect();
+                options.selected_channels = Some(channels.ok()?);
+            }
+            _ => return None,
+        }
+    }
+    options.is_supported().then_some(options)
+}
+
+/// Derandomization, FEC and output of the packets found after the syncword
+struct PacketDecoder {
+    /// CCSDS Reed-Solomon codec and interleave depth
+    reed_solomon: Option<(ReedSolomon, usize)>,
+    ldpc: Option<(LdpcDecoder, LdpcCode)>,
+    turbo: Option<TurboDecoder>,
+    stats: FrameStats,
+    crc: Option<FrameCheck>,
+    crc_stats: CrcStats,
+    /// Whether TM frames end with a FECF and the frame counts seen so far
+    tm: Option<(bool, TmDemultiplexer)>,
+    /// Drop packets failing the RS decoder or the CRC without a trace, they are
+    /// overlapping candidates of another packet
+    valid_only: bool,
+}
+
+impl PacketDecoder {
+    fn new(options: &Options) -> Self {
+        Self {
+            reed_solomon: options.rs_depth.map(|depth| (ReedSolomon::ccsds(), depth)),
+            ldpc: options.ldpc.as_ref().map(|code| {
+                let decoder = LdpcDecoder::new(code.h.clone(), options.ldpc_iterations)
+                    .with_fill_bits(code.fill);
+                (decoder, code.clone())
+            }),
+            turbo: options
+                .turbo
+                .and_then(|(k, rate)| TurboDecoder::new(k, rate, options.turbo_iterations)),
+            stats: FrameStats::default(),
+            crc: options.crc.clone(),
+            crc_stats: CrcStats::default(),
+            tm: options.tm.map(|fecf| (fecf, TmDemultiplexer::new())),
+            valid_only: matches!(options.overlap, OverlapPolicy::Parallel { .. }),
+        }
+    }
+
+    /// Returns whether the packet was printed, see `valid_only`
+    fn handle<S: SoftBit>(
+        &mut self,
+        sample_idx: isize,
+        channel: Option<usize>,
+        packet: &mut [S],
+    ) -> bool {
+        LSFR::<u8>::new(0b10101001, 0xFF).derandomize(packet);
+
+        // The decoding steps are only printed once the packet is known to be kept
+        let mut report = Vec::new();
+        let mut packet_bytes = match (self.ldpc.as_mut(), self.turbo.as_ref()) {
+            (Some((ldpc, code)), _) => {
+                BitStreamDecoder::BE.decode(&Self::decode_ldpc(ldpc, code, packet, &mut report))
+            }
+            (None, Some(turbo)) => {
+                BitStreamDecoder::BE.decode(&Self::decode_turbo(turbo, packet, &mut report))
+            }
+            (None, None) => BitStreamDecoder::BE.decode(packet),
+        };
+        let mut trusted = true;
+        let mut quality = None;
+        if let Some((rs, depth)) = &self.reed_solomon {
+            let frame_quality;
+            (packet_bytes, frame_quality) = Self::correct(rs, *depth, packet_bytes, &mut report);
+            report.push(format!("frame quality: {}", frame_quality));
+            trusted &= frame_quality != FrameQuality::Uncorrectable;
+            quality = Some(frame_quality);
+        }
+        let crc_passed = self.crc.as_ref().map(|crc| crc.check(&packet_bytes));
+        trusted &= crc_passed != Some(false);
+        if self.valid_only && !trusted {
+            return false;
+        }
+
+        match channel {
+            None => println!("packet @ {:#6}:", sample_idx),
+            Some(channel) => println!("packet @ {:#6} (channel {}):", sample_idx, channel),
+        }
+        // println!("    {:?}", &packet);
+        for line in report {
+            println!("    {}", line);
+        }
+        if let Some(quality) = quality {
+            self.stats.record(quality);
+        }
+        if let (Some(crc), Some(passed)) = (&self.crc, crc_passed) {
+            self.crc_stats.record(passed);
+            match (passed, crc.drop) {
+                (true, _) => println!("    CRC: ok"),
+                (false, false) => println!("    CRC: mismatch"),
+                (false, true) => {
+                    println!("    CRC: mismatch, frame dropped");
+                    return true;
+                }
+            }
+        }
+        match self.tm.as_mut() {
+            Some((fecf, demux)) => Self::print_tm_frame(demux, *fecf, trusted, &packet_bytes),
+            None => println!("    {}", Bytes(&packet_bytes)),
+        }
+        true
+    }
+
+    /// Prints the fields of a TM transfer frame and the frame count gaps on its
+    /// master and virtual channel. Frames that are not `trusted` (uncorrectable by the RS
+    /// decoder or failing the CRC) or that fail the FECF check do not update the counts.
+    fn print_tm_frame(demux: &mut TmDemultiplexer, fecf: bool, trusted: bool, bytes: &[u8]) {
+        let Some(frame) = TmFrame::parse(bytes, fecf) else {
+            println!("    TM: frame too short");
+            println!("    {}", Bytes(bytes));
+            return;
+        };
+        println!("    TM: {}", frame.header);
+        if trusted && frame.fecf_valid != Some(false) {
+            let gaps = demux.record(&frame.header);
+            if gaps.master_channel > 0 {
+                println!(
+                    "    MC frame count gap: {} frames missing",
+                    gaps.master_channel
+                );
+            }
+            if gaps.virtual_channel > 0 {
+                println!(
+                    "    VC frame count gap: {} frames missing",
+                    gaps.virtual_channel
+                );
+            }
+        }
+        match frame.ocf {
+            Some(Ocf::Clcw(clcw)) => println!("    CLCW: {}", clcw),
+            Some(Ocf::Other(word)) => println!("    OCF: {:#010x}", word),
+            None => {}
+        }
+        match frame.fecf_valid {
+            Some(true) => println!("    FECF: ok"),
+            Some(false) => println!("    FECF: mismatch"),
+            None => {}
+        }
+        if let Some(secondary_header) = frame.secondary_header {
+            println!("    secondary header: {}", Bytes(secondary_header));
+        }
+        println!("    {}", Bytes(frame.data));
+    }
+
+    /// Returns the whole information bytes of the codeword, with the appended bits
+    /// stripped before decoding and the fill bits dropped after it
+    fn decode_ldpc<S: SoftBit>(
+        ldpc: &mut LdpcDecoder,
+        code: &LdpcCode,
+        packet: &[S],
+        report: &mut Vec<String>,
+    ) -> Vec<bool> {
+        let mut codeword = Vec::new();
+        let transmitted = &packet[..packet.len().saturating_sub(code.appended)];
+        report.push(match ldpc.decode(transmitted, &mut codeword) {
+            Some(iterations) => format!("LDPC: valid codeword after {} iterations", iterations),
+            None => "LDPC: no valid codeword".to_string(),
+        });
+        code.info_bits(&codeword)
+    }
+
+    fn decode_turbo<S: SoftBit>(
+        turbo: &TurboDecoder,
+        packet: &[S],
+        report: &mut Vec<String>,
+    ) -> Vec<bool> {
+        let mut info = Vec::new();
+        report.push(match turbo.decode(packet, &mut info) {
+            Some(iterations) => format!("turbo: converged after {} iterations", iterations),
+            None => "turbo: not converged".to_string(),
+        });
+        info
+    }
+
+    /// Prints the frame statistics, if the frames were RS decoded, CRC checked or parsed as TM frames
+    fn finish(&self) {
+        if self.reed_solomon.is_some() {
+            println!("{}", self.stats);
+        }
+        if self.crc.is_some() {
+            println!("{}", self.crc_stats);
+        }
+        if let Some((_, demux)) = &self.tm {
+            print!("{}", demux);
+        }
+    }
+
+    /// Corrects the interleaved codewords and strips the parity symbols
+    fn correct(
+        rs: &ReedSolomon,
+        depth: usize,
+        mut frame: Vec<u8>,
+        report: &mut Vec<String>,
+    ) -> (Vec<u8>, FrameQuality) {
+        let codeword_len = frame.len() / depth;
+        if !frame.len().is_multiple_of(depth) || codeword_len <= rs.nroots() || codeword_len > 255 {
+            report.push(format!(
+                "RS: {} bytes do not fit interleave depth {}",
+                frame.len(),
+                depth
+            ));
+            return (frame, FrameQuality::Uncorrectable);
+        }
+
+        let results = rs.decode_interleaved(&mut frame, depth, &[]);
+        let quality = FrameQuality::from_rs(&results);
+        let results: Vec<String> = results
+            .iter()
+            .map(|result| match result {
+                Some(corrections) => corrections.total().to_string(),
+                None => "-".to_string(),
+            })
+            .collect();
+        report.push(format!(
+            "RS: {} symbols corrected per codeword (- uncorrectable)",
+            results.join(" ")
+        ));
+        frame.truncate((codeword_len - rs.nroots()) * depth);
+        (frame, quality)
+    }
+}
+
+fn print_phase_variant(variant: Option<SyncwordVariant>) {
+    match variant {
+        Some(variant) if !variant.is_identity() => println!(
+            "    phase: {}°{}",
+            variant.rotation_deg,
+            if variant.conjugate {
+                " (conjugated)"
+            } else {
+                ""
+            }
+        ),
+        _ => {}
+    }
+}
+
+fn print_viterbi_state(viterbi: &ConvolutionalDecoder) {
+    let phase = viterbi.code_phase();
+    if phase.offset != 0 || phase.rotated {
+        println!(
+            "    code phase: offset {}{}",
+            phase.offset,
+            if phase.rotated { ", 90°" } else { "" }
+        );
+    }
+    println!("    viterbi metric: {:.3}", viterbi.quality());
+}
+
+fn print_ber_event(sample_idx: isize, event: BerEvent) {
+    match event {
+        BerEvent::SyncAcquired { inverted, slip, .. } => {
+            print!("PRBS sync @ {:#6}", sample_idx);
+            if inverted {
+                print!(", inverted");
+            }
+            match slip {
+                Some(0) | None => println!(),
+                Some(slip) => println!(", slip of {:+} bits", slip),
+            }
+        }
+        BerEvent::SyncLost { .. } => println!("PRBS sync lost @ {:#6}", sample_idx),
+        BerEvent::Report(stats) => println!("BER @ {:#6}: {}", sample_idx, stats),
+    }
+}
+
+fn run_channelized<T: Read>(source: &mut T, options: &Options, num_channels: usize) {
+    let mut channelizer = PolyphaseChannelizer::new(num_channels, 16);
+    let mut decoder = PacketDecoder::new(options);
+    let selected = match &options.selected_channels {
+        Some(selected) => selected.clone(),
+        None => (0..num_channels).collect(),
+    };
+    let mut chains: Vec<ChannelChain> = selected
+        .into_iter()
+        .map(|channel| {
+            ChannelChain::new(
+                channel,
+                options.sps / num_channels as f32,
+                options.modulation,
+                db_to_gain(options.agc_max_gain_db),
+            )
+        })
+        .collect();
+
+    let mut front_end = options.iq_correction.then(FrontEndCorrection::new);
+    let mut sample_idx = -1isize;
+    while let Some(mut sample) = read_complex_value(source) {
+        sample_idx += 1;
+        if let Some(front_end) = front_end.as_mut() {
+            sample = front_end.tick(sample);
+        }
+        let channel_samples = match channelizer.tick(sample) {
+            None => continue,
+            Some(channel_samples) => channel_samples,
+        };
+
+        for chain in chains.iter_mut() {
+            let channel = chain.channel;
+            let symbol = match chain.tick(channel_samples[channel]) {
+                None => continue,
+                Some(symbol) => symbol,
+            };
+            for bit in chain.constellation.symbol_bits(symbol) {
+                if let Some(packet) = chain.syncword_packetizer.tick(bit) {
+                    decoder.handle(sample_idx, Some(channel), packet);
+                    print_phase_variant(chain.syncword_packetizer.matched_variant());
+                }
+            }
+        }
+    }
+    decoder.finish();
+}
+
+/// Soft correlator for the given threshold, hard decision scan with one bit error per
+/// 32 syncword bits otherwise
+fn syncword_detector<S: SoftBit>(
+    syncword: &[bool],
+    threshold: Option<f32>,
+    ambiguity: PhaseAmbiguity,
+) -> Box<dyn SyncwordDetector<S>> {
+    fn correlator<S: SoftBit, const N: usize>(
+        syncword: &[bool],
+        threshold: f32,
+        ambiguity: PhaseAmbiguity,
+    ) -> Box<dyn SyncwordDetector<S>> {
+        let syncword = syncword.try_into().expect("Unexpected syncword length!");
+        Box::new(SoftSyncwordCorrelator::<N>::new(
+            syncword, threshold, ambiguity,
+        ))
+    }
+
+    let threshold = match threshold {
+        None => {
+            let error_thresh = syncword.len() as u32 / 32;
+            return Box::new(PackedSyncwordScan::from_bits(
+                syncword,
+                error_thresh,
+                ambiguity,
+            ));
+        }
+        Some(threshold) => threshold,
+    };
+    // The lengths of the CCSDS ASMs
+    match syncword.len() {
+        32 => correlator::<S, 32>(syncword, threshold, ambiguity),
+        64 => correlator::<S, 64>(syncword, threshold, ambiguity),
+        96 => correlator::<S, 96>(syncword, threshold, ambiguity),
+        128 => correlator::<S, 128>(syncword, threshold, ambiguity),
+        192 => correlator::<S, 192>(syncword, threshold, ambiguity),
+        len => panic!("No soft correlator for {} bit syncwords", len),
+    }
+}
+
+/// Runs the single carrier chain, `demap` turns each recovered symbol into (soft) bits
+fn run_single_channel<T, S, D>(source: &mut T, options: &Options, mut demap: D)
+where
+    T: Read,
+    S: SoftBit,
+    D: FnMut(Complex32, &mut Vec<S>),
+{
+    let mut decoder = PacketDecoder::new(options);
+    let mut acg_filter = AGC::new(0.05, 0.01, 1.0, 0.0, db_to_gain(options.agc_max_gain_db));
     let mut roll_avg_filter = FIRFilter::new([Complex32::from(0.2); 5]);
-    let mut symbol_sync = SymbolSync::new(5.0, GardnerErrorEstimator {}, 0.0, 0.0);
-    let mut syncword_packetizer: SyncwordPacketizer<bool, 32, 10200> =
-        SyncwordPacketizer::new(SYNCWORD, 1);
-    let bitstream_decoder = BitStreamDecoder::BE;
-    let mut bitstream: Vec<u8> = Vec::new();
+    let mut oqpsk_aligner = options.modulation.aligner(options.sps);
+    let mut fsk_demodulator = options
+        .modulation
+        .fsk_demodulator(options.sps, options.fsk_index);
+    let mut symbol_sync = SymbolSync::new(options.sps, GardnerErrorEstimator {}, 0.0, 0.0);
+    let mut differential_detector = options
+        .differential
+        .then(|| DifferentialDetector::new(&options.modulation.constellation()));
+    let mut line_decoder = LineDecoder::new(options.line_code);
+    // Differential decoding already removes the phase ambiguity
+    let ambiguity = if options.differential || options.line_code != LineCode::NrzL {
+        PhaseAmbiguity::None
+    } else {
+        options.modulation.phase_ambiguity()
+    };
+    let mut viterbi = options.viterbi.as_ref().map(|(code, puncturing)| {
+        ConvolutionalDecoder::new(code, puncturing, ambiguity == PhaseAmbiguity::Qpsk)
+    });
+    // The Viterbi decoder resolves QPSK rotations, inverted symbols only invert the decoded bits
+    let ambiguity = match ambiguity {
+        PhaseAmbiguity::None => PhaseAmbiguity::None,
+        _ if viterbi.is_some() => PhaseAmbiguity::Bpsk,
+        ambiguity => ambiguity,
+    };
+    // Turbo codewords have their own, rate dependent ASM
+    let syncword = match options.turbo {
+        Some((_, rate)) => hex_bits(rate.asm()).unwrap(),
+        None => SYNCWORD.to_vec(),
+    };
+    let detector = syncword_detector(&syncword, options.sync_threshold, ambiguity);
+    let mut syncword_packetizer: Box<dyn Packetizer<S>> = match options.length_field {
+        Some(field) => Box::new(LengthFieldPacketizer::new(detector, field)),
+        None => Box::new(
+            SyncwordPacketizer::new(detector, options.packet_len())
+                .with_overlap_policy(options.overlap),
+        ),
+    };
+    let mut ber_tester = options.ber.map(BerTester::new);
+    let mut front_end = options.iq_correction.then(FrontEndCorrection::new);
+    let mut squelch = options
+        .squelch_db
+        .map(|open_db| Squelch::new(0.01, open_db, open_db - SQUELCH_HYSTERESIS_DB));
+    let mut bits = Vec::new();
     let mut sample_idx = -1isize;
     loop {
         sample_idx += 1;
-        let sample = match read_complex_value(&mut source) {
+        let mut sample = match read_complex_value(source) {
             Some(val) => val,
             None => break,
         };
+        if let Some(front_end) = front_end.as_mut() {
+            sample = front_end.tick(sample);
+        }
+
+        if let Some(squelch) = squelch.as_mut() {
+            match squelch.tick(sample) {
+                Some(SquelchEvent::BurstStart { .. }) => {
+                    symbol_sync.reset();
+                    syncword_packetizer.reset();
+                    if let Some(viterbi) = viterbi.as_mut() {
+                        viterbi.reset();
+                    }
+                }
+                Some(SquelchEvent::BurstEnd { start_idx, end_idx }) => {
+                    println!("burst @ {:#6}..{:#6}", start_idx, end_idx);
+                }
+                None => {}
+            }
+            if !squelch.is_open() {
+                continue;
+            }
+        }
 
         let acg_sample = acg_filter.tick(sample);
-        let roll_avg_out = roll_avg_filter.tick(acg_sample);
+        let mut roll_avg_out = match fsk_demodulator.as_mut() {
+            Some(fsk_demodulator) => Complex32::new(fsk_demodulator.tick(acg_sample), 0.0),
+            None => roll_avg_filter.tick(acg_sample),
+        };
+        if let Some(aligner) = oqpsk_aligner.as_mut() {
+            roll_avg_out = aligner.tick(roll_avg_out);
+        }
         // println!(
         //     "{:?}, norm: {:?} (before: {:?})",
         //     roll_avg_out,
@@ -300,32 +1128,154 @@ fn main() -> Result<(), ()> {
         //     sample.norm()
         // );
 
-        let symbol_out = match symbol_sync.tick(roll_avg_out) {
+        let mut symbol_out = match symbol_sync.tick(roll_avg_out) {
             None => continue,
             Some(symbol_out) => symbol_out,
         };
+        if let Some(detector) = differential_detector.as_mut() {
+            symbol_out = detector.tick(symbol_out);
+        }
         // println!("{}", symbol_out);
-        let val = symbol_out.re >= 0.0;
-        let packet = match syncword_packetizer.tick(val) {
+        bits.clear();
+        demap(symbol_out, &mut bits);
+        for bit in bits.iter() {
+            let mut bit = line_decoder.tick(*bit);
+            if let Some(viterbi) = viterbi.as_mut() {
+                bit = match viterbi.tick(bit) {
+                    None => continue,
+                    Some(bit) => bit,
+                };
+            }
+            if let Some(ber_tester) = ber_tester.as_mut() {
+                if let Some(event) = ber_tester.tick(bit.hard()) {
+                    print_ber_event(sample_idx, event);
+                }
+                continue;
+            }
+            if let Some(packet) = syncword_packetizer.tick(bit) {
+                let reliability =
+                    packet.iter().map(|bit| bit.llr().abs()).sum::<f32>() / packet.len() as f32;
+                if !decoder.handle(sample_idx, None, packet) {
+                    continue;
+                }
+                print_phase_variant(syncword_packetizer.matched_variant());
+                match viterbi.as_ref() {
+                    Some(viterbi) => print_viterbi_state(viterbi),
+                    None if options.soft => println!("    mean |LLR|: {:.2}", reliability),
+                    None => {}
+                }
+            }
+        }
+    }
+    decoder.finish();
+    if let Some(invalid) = syncword_packetizer.invalid_lengths() {
+        println!("Length field: {} frames with an invalid length", invalid);
+    }
+    if let Some(ber_tester) = ber_tester {
+        println!("BER: {}", ber_tester.stats());
+    }
+}
+
+/// AFSK1200 (Bell-202) packet radio chain: FM demodulation (unless the input
+/// already is audio), tone demodulation, clock recovery, NRZI decoding and HDLC deframing
+fn run_afsk<T: Read>(source: &mut T, options: &Options) {
+    let sps = Bell202Demodulator::samples_per_symbol(options.sample_rate);
+    let mut fm_demod = QuadratureDemod::new(1.0);
+    let mut tone_demod = Bell202Demodulator::new(options.sample_rate);
+    let mut symbol_sync = SymbolSync::new(sps, GardnerErrorEstimator {}, 0.02, 1.0);
+    let mut nrzi = LineDecoder::new(LineCode::NrzS);
+    let mut deframer = HdlcDeframer::new();
+    let mut kiss_server = options.kiss_addr.as_ref().map(|addr| {
+        let server = KissServer::bind(addr).expect("Failed to bind KISS server");
+        println!("KISS server listening on {}", server.local_addr().unwrap());
+        server
+    });
+
+    let mut sample_idx = -1isize;
+    loop {
+        sample_idx += 1;
+        let audio = if options.audio_input {
+            read_real_value(source)
+        } else {
+            read_complex_value(source).map(|sample| fm_demod.tick(sample))
+        };
+        let audio = match audio {
+            Some(audio) => audio,
+            None => break,
+        };
+
+        let tone = tone_demod.tick(audio);
+        let symbol_out = match symbol_sync.tick(Complex32::new(tone, 0.0)) {
+            None => continue,
+            Some(symbol_out) => symbol_out,
+        };
+        let frame = match deframer.tick(nrzi.tick(symbol_out.re >= 0.0)) {
             None => continue,
-            Some(pkt) => pkt,
+            Some(frame) => frame,
         };
 
-        let mut prng_lsfr = LSFR::<u8>::new(0b10101001, 0xFF);
-        for bit_idx in 0..packet.len() {
-            packet[bit_idx] ^= prng_lsfr.next().unwrap();
+        println!("frame @ {:#6}:", sample_idx);
+        match Ax25Frame::parse(frame) {
+            Some(ax25_frame) => println!("    {}", ax25_frame),
+            None => println!("    {}", Bytes(frame)),
         }
+        if let Some(kiss_server) = kiss_server.as_mut() {
+            kiss_server.send_frame(0, frame);
+        }
+    }
 
-        println!("packet @ {:#6}:", sample_idx);
-        // println!("    {:?}", &packet);
+    println!("HDLC frames: {}", deframer.stats());
+}
+
+fn main() -> Result<(), ()> {
+    let options = match parse_args() {
+        Some(options) => options,
+        None => {
+            eprintln!("{}", USAGE);
+            return Err(());
+        }
+    };
+    let mut source =
+        BufReader::new(open_source_file(&options.filename).expect("Failed to open source-file!"));
+
+    if options.afsk {
+        run_afsk(&mut source, &options);
+        return Ok(());
+    }
+
+    if let Some(num_channels) = options.num_channels {
+        run_channelized(&mut source, &options, num_channels);
+        return Ok(());
+    }
 
-        let packet_bytes = bitstream_decoder.decode(&packet);
-        println!("    {}", Bytes(&packet_bytes));
+    let constellation = options.modulation.constellation();
+    if options.soft_i8 {
+        let mut noise_estimator = NoiseVarianceEstimator::from_alpha(0.01);
+        run_single_channel(&mut source, &options, |symbol, bits: &mut Vec<i8>| {
+            let noise_var = noise_estimator.tick(&constellation, symbol);
+            let scale = i8_llr_scale(&constellation, noise_var);
+            bits.extend(
+                constellation
+                    .soft_bits(symbol, noise_var)
+                    .map(|llr| quantize_llr(llr, scale)),
+            );
+        });
+    } else if options.soft {
+        let mut noise_estimator = NoiseVarianceEstimator::from_alpha(0.01);
+        run_single_channel(&mut source, &options, |symbol, bits: &mut Vec<f32>| {
+            let noise_var = noise_estimator.tick(&constellation, symbol);
+            bits.extend(constellation.soft_bits(symbol, noise_var));
+        });
+    } else {
+        run_single_channel(&mut source, &options, |symbol, bits: &mut Vec<bool>| {
+            bits.extend(constellation.symbol_bits(constellation.decide(symbol)));
+        });
     }
 
+    let bitstream: Vec<u8> = Vec::new();
     std::fs::File::create("bitstream.out")
         .expect("Failed to create bitstream")
-        .write_all(&mut bitstream)
+        .write_all(&bitstream)
         .expect("Failed to write bitstream");
 
     Ok(())
@@ -1,13 +1,15 @@
 use std::f32::consts::PI;
 
 use crate::{clock::SimpleClock, DiscretePI};
-use num::complex::{Complex32, ComplexFloat};
+use num::complex::Complex32;
 
+#[allow(dead_code)]
 struct PLL {
     internal_clock: SimpleClock,
     phase_controller: DiscretePI<f32, f32>,
 }
 
+#[allow(dead_code)]
 impl PLL {
     fn new(rate: f32, relative_noise_bandwidth: f32, dampening_factor: f32) -> Self {
         let kp = (4.0 * dampening_factor) / (dampening_factor + 1.0 / (4.0 * dampening_factor))
@@ -45,7 +47,7 @@ impl PLL {
 
 #[cfg(test)]
 mod tests {
-    use crate::test_utils::{self, create_output_directory};
+    use crate::test_utils::create_output_directory;
     use crate::{clock::SimpleClock, pll::PLL};
     use std::f32::consts::PI;
 
@@ -1,5 +1,5 @@
 use core::ops::Index;
-use std::{ops::IndexMut, slice::SliceIndex};
+use std::ops::IndexMut;
 
 #[derive(Debug)]
 pub struct RingBuffer<T: Sized, const N: usize> {
@@ -23,6 +23,7 @@ impl<T, const N: usize> RingBuffer<T, N>
 where
     T: Sized + Copy,
 {
+    #[cfg(test)]
     pub fn with_init_val(val: T) -> Self {
         Self {
             current_write_idx: 0,
@@ -42,7 +43,7 @@ impl<T: Sized, const N: usize> Index<isize> for RingBuffer<T, N> {
         buf_idx -= (buf_idx >= (N as isize)) as isize * (N as isize);
         buf_idx += (buf_idx < 0) as isize * (N as isize);
 
-        return &self.buffer[buf_idx as usize];
+        &self.buffer[buf_idx as usize]
     }
 }
 
@@ -55,7 +56,7 @@ impl<T: Sized, const N: usize> Index<usize> for RingBuffer<T, N> {
 
         let mut buf_idx = index + self.current_write_idx;
         buf_idx -= (buf_idx >= N) as usize * N;
-        return &self.buffer[buf_idx];
+        &self.buffer[buf_idx]
     }
 }
 
@@ -69,7 +70,7 @@ impl<T: Sized, const N: usize> IndexMut<isize> for RingBuffer<T, N> {
         buf_idx -= (buf_idx >= (N as isize)) as isize * (N as isize);
         buf_idx += (buf_idx < 0) as isize * (N as isize);
 
-        return &mut self.buffer[buf_idx as usize];
+        &mut self.buffer[buf_idx as usize]
     }
 }
 
@@ -81,7 +82,7 @@ impl<T: Sized, const N: usize> IndexMut<usize> for RingBuffer<T, N> {
 
         let mut buf_idx = index + self.current_write_idx;
         buf_idx -= (buf_idx >= N) as usize * N;
-        return &mut self.buffer[buf_idx];
+        &mut self.buffer[buf_idx]
     }
 }
 
@@ -1,26 +1,192 @@
-use num::PrimInt;
+use num::{PrimInt, Unsigned};
+
+use crate::soft_bit::SoftBit;
+
+/// Feedback structure of an `LSFR`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LsfrForm {
+    /// The parity of the tapped state bits is shifted in at the top
+    Fibonacci,
+    /// `poly` is XORed onto the state after every shift that outputs a one
+    #[allow(dead_code)]
+    Galois,
+}
+
+/// Linear feedback shift register, the output is the lowest state bit and the
+/// register shifts right.
+/// `poly` is a mask on the state register in both forms, a Galois register with
+/// the bit reversed mask generates the same sequence as the Fibonacci register.
+/// E.g. x^15 + x^14 + 1 is `0b11` in Fibonacci and `0x6000` in Galois form.
 pub struct LSFR<T> {
     poly: T,
     state: T,
+    width: u32,
+    form: LsfrForm,
+    /// Output byte and state after 8 steps from each value of each state byte,
+    /// built on the first `next_byte`
+    byte_table: Vec<(u8, T)>,
 }
 
-impl<T> LSFR<T> {
+impl<T: PrimInt + Unsigned> LSFR<T> {
+    /// Fibonacci register spanning all bits of `T`
     pub fn new(poly: T, state: T) -> Self {
-        Self { poly, state }
+        Self {
+            poly,
+            state,
+            width: T::zero().count_zeros(),
+            form: LsfrForm::Fibonacci,
+            byte_table: Vec::new(),
+        }
+    }
+
+    /// Register length in bits, at most the width of `T`
+    pub fn with_width(mut self, width: u32) -> Self {
+        assert!(width >= 1 && width <= T::zero().count_zeros());
+        self.width = width;
+        self.byte_table.clear();
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_form(mut self, form: LsfrForm) -> Self {
+        self.form = form;
+        self.byte_table.clear();
+        self
+    }
+
+    pub fn state(&self) -> T {
+        self.state
+    }
+
+    fn step(&mut self) -> bool {
+        let out = self.state & T::one() == T::one();
+        match self.form {
+            LsfrForm::Fibonacci => {
+                let feedback = T::from((self.state & self.poly).count_ones() & 1).unwrap();
+                self.state = (self.state >> 1) | (feedback << (self.width as usize - 1));
+            }
+            LsfrForm::Galois => {
+                self.state = self.state >> 1;
+                if out {
+                    self.state = self.state ^ self.poly;
+                }
+            }
+        }
+        out
+    }
+
+    fn step_byte(&mut self) -> u8 {
+        (0..8).fold(0u8, |acc, _| (acc << 1) | self.step() as u8)
+    }
+
+    /// Both forms are linear in the state, so 8 steps from any state are the XOR
+    /// of the table entries of its bytes
+    fn build_byte_table(&self) -> Vec<(u8, T)> {
+        let state_bytes = self.width.div_ceil(8) as usize;
+        let mut table = Vec::with_capacity(state_bytes * 256);
+        for state_byte in 0..state_bytes {
+            for value in 0..=u8::MAX {
+                let mut register = Self {
+                    poly: self.poly,
+                    state: T::from(value).unwrap() << (8 * state_byte),
+                    width: self.width,
+                    form: self.form,
+                    byte_table: Vec::new(),
+                };
+                table.push((register.step_byte(), register.state));
+            }
+        }
+        table
+    }
+
+    /// Next 8 bits of the sequence, the first one in the MSB.
+    /// Table driven, with one lookup per byte of the register.
+    #[allow(dead_code)]
+    pub fn next_byte(&mut self) -> u8 {
+        if self.byte_table.is_empty() {
+            self.byte_table = self.build_byte_table();
+        }
+        let mut output = 0;
+        let mut state = T::zero();
+        for (state_byte, entries) in self.byte_table.chunks(256).enumerate() {
+            let value = (self.state >> (8 * state_byte)) & T::from(u8::MAX).unwrap();
+            let (byte_output, byte_state) = entries[value.to_usize().unwrap()];
+            output ^= byte_output;
+            state = state ^ byte_state;
+        }
+        self.state = state;
+        output
+    }
+
+    /// XORs the sequence onto `bits`, soft bits are inverted where the sequence is set
+    pub fn derandomize<S: SoftBit>(&mut self, bits: &mut [S]) {
+        for bit in bits.iter_mut() {
+            if self.step() {
+                *bit = bit.invert();
+            }
+        }
+    }
+
+    /// XORs the sequence onto bytes transmitted MSB first
+    #[allow(dead_code)]
+    pub fn derandomize_bytes(&mut self, bytes: &mut [u8]) {
+        for byte in bytes.iter_mut() {
+            *byte ^= self.next_byte();
+        }
     }
 }
 
-impl Iterator for LSFR<u8> {
+impl<T: PrimInt + Unsigned> Iterator for LSFR<T> {
     type Item = bool;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let out = (self.state & 1u8) != 0;
-        let new_bit = (self.state & self.poly).count_ones() as u8 & 1u8;
-        self.state = (new_bit << 7) | (self.state >> 1);
-        Some(out)
+        Some(self.step())
     }
 }
 
+/// Multiplicative (self-synchronizing) scrambler, each output bit is the input
+/// XORed with the tapped previous output bits.
+/// The descrambler taps the received bits instead and recovers the data after
+/// as many bits as the longest tap delay, independent of its initial state.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct MultiplicativeScrambler {
+    /// Bit `i` taps the bit delayed by `i + 1`
+    taps: u64,
+    history: u64,
+}
+
+#[allow(dead_code)]
+impl MultiplicativeScrambler {
+    pub fn new(taps: u64) -> Self {
+        Self { taps, history: 0 }
+    }
+
+    /// 1 + x^12 + x^17 as used by G3RUH 9600 baud packet radio
+    pub fn g3ruh() -> Self {
+        Self::new((1 << 11) | (1 << 16))
+    }
+
+    fn feedback(&self) -> bool {
+        (self.history & self.taps).count_ones() & 1 != 0
+    }
+
+    #[cfg(test)]
+    pub fn scramble(&mut self, bit: bool) -> bool {
+        let out = bit ^ self.feedback();
+        self.history = (self.history << 1) | out as u64;
+        out
+    }
+
+    /// Soft bits are inverted where the hard decisions of the tapped bits have odd parity
+    pub fn descramble<S: SoftBit>(&mut self, bit: S) -> S {
+        let out = if self.feedback() { bit.invert() } else { bit };
+        self.history = (self.history << 1) | bit.hard() as u64;
+        out
+    }
+}
+
+#[cfg(test)]
 macro_rules! bitstream {
     (@parse 1) => { true  };
     (@parse 0) => { false };
@@ -35,15 +201,121 @@ macro_rules! bitstream {
 
 #[cfg(test)]
 mod tests {
-    use super::LSFR;
+    use super::{LsfrForm, MultiplicativeScrambler, LSFR};
+    use crate::test_utils::TestRng;
+
+    #[test]
+    fn test_lsfr_derandomize_soft() {
+        let mut reference = LSFR::<u8>::new(0b10101001, 0xFF);
+        let mut soft = [1.5f32; 16];
+        LSFR::<u8>::new(0b10101001, 0xFF).derandomize(&mut soft);
+        for val in soft {
+            assert_eq!(val < 0.0, reference.next().unwrap());
+        }
+    }
 
     #[test]
     fn test_lsfr() {
-        let mut lsfr = LSFR::new(0b10101001, 0xFF);
+        let mut lsfr = LSFR::<u8>::new(0b10101001, 0xFF);
         let expected_stream = bitstream!(1 1 1 1 1 1 1 1 0 1 0 0 1 0 0 0 0 0 0 0 1 1 1 0 1 1 0 0 0 0 0 0 1 0 0 1 1 0 1 0);
 
         for i in expected_stream {
             assert_eq!(lsfr.next().unwrap(), *i);
         }
     }
+
+    #[test]
+    fn test_lsfr_bytes() {
+        let mut bits = LSFR::<u8>::new(0b10101001, 0xFF);
+        let mut bytes = LSFR::<u8>::new(0b10101001, 0xFF);
+        // Start of the CCSDS pseudo-randomizer sequence
+        assert_eq!(bytes.next_byte(), 0xFF);
+        assert_eq!(bytes.next_byte(), 0x48);
+        assert_eq!(bytes.next_byte(), 0x0E);
+        assert_eq!(bytes.next_byte(), 0xC0);
+
+        let mut data = [0x5Au8; 300];
+        LSFR::<u8>::new(0b10101001, 0xFF).derandomize_bytes(&mut data);
+        for byte in data {
+            let expected = (0..8).fold(0u8, |acc, _| (acc << 1) | bits.next().unwrap() as u8);
+            assert_eq!(byte ^ 0x5A, expected);
+        }
+    }
+
+    #[test]
+    fn test_lsfr_byte_table_matches_steps() {
+        let mut rng = TestRng::new(48);
+        // PRBS23 and PRBS31 in both forms, and a full width register
+        let cases = [
+            (0x21u64, 23, LsfrForm::Fibonacci),
+            (0x21 << 17, 23, LsfrForm::Galois),
+            (0x9, 31, LsfrForm::Fibonacci),
+            (0x9 << 27, 31, LsfrForm::Galois),
+            (0xD800_0000_0000_0000, 64, LsfrForm::Galois),
+        ];
+        for (poly, width, form) in cases {
+            let state = rng.next_u64() >> (64 - width);
+            let mut bytes = LSFR::new(poly, state).with_width(width).with_form(form);
+            let mut bits = LSFR::new(poly, state).with_width(width).with_form(form);
+            for _ in 0..100 {
+                let expected = bits.step_byte();
+                assert_eq!(bytes.next_byte(), expected, "{poly:#x}");
+                assert_eq!(bytes.state(), bits.state());
+            }
+        }
+    }
+
+    #[test]
+    fn test_lsfr_dvb_prbs() {
+        // 1 + x^14 + x^15, loaded with 100101010000000 in stages 1 to 15.
+        // The DVB output is the feedback, which leaves the last stage 15 bits later.
+        let mut lsfr = LSFR::<u16>::new(0b11, 0x4A80).with_width(15);
+        lsfr.by_ref().take(15).for_each(drop);
+        let expected = bitstream!(0 0 0 0 0 0 1 1 1 1 1 1 0 1 1 0 0 0 0 0 1 0 0 0);
+        for bit in expected {
+            assert_eq!(lsfr.next().unwrap(), *bit);
+        }
+
+        for form in [LsfrForm::Fibonacci, LsfrForm::Galois] {
+            let poly = match form {
+                LsfrForm::Fibonacci => 0b11,
+                LsfrForm::Galois => 0x6000,
+            };
+            let mut lsfr = LSFR::<u64>::new(poly, 1).with_width(15).with_form(form);
+            let sequence: Vec<bool> = lsfr.by_ref().take(32767).collect();
+            // Maximal length sequence satisfying the recurrence of the polynomial
+            assert_eq!(lsfr.state(), 1, "{form:?}");
+            assert_eq!(sequence.iter().filter(|bit| **bit).count(), 1 << 14);
+            for idx in 0..1000 {
+                assert_eq!(sequence[idx + 15], sequence[idx] ^ sequence[idx + 1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_g3ruh_scrambler() {
+        let mut scrambler = MultiplicativeScrambler::g3ruh();
+        let impulse: Vec<usize> = (0..60)
+            .filter(|idx| scrambler.scramble(*idx == 0))
+            .collect();
+        assert_eq!(impulse, [0, 12, 17, 24, 34, 36, 41, 46, 48, 51]);
+
+        let mut rng = TestRng::new(48);
+        let data: Vec<bool> = (0..500).map(|_| rng.next_bool()).collect();
+        let mut scrambler = MultiplicativeScrambler::g3ruh();
+        let scrambled: Vec<bool> = data.iter().map(|bit| scrambler.scramble(*bit)).collect();
+
+        // The descrambler starts in a different state and synchronizes after 17 bits
+        let mut descrambler = MultiplicativeScrambler::g3ruh();
+        for _ in 0..17 {
+            descrambler.descramble(rng.next_bool());
+        }
+        let descrambled: Vec<f32> = scrambled[100..]
+            .iter()
+            .map(|bit| descrambler.descramble(if *bit { 1.0f32 } else { -1.0 }))
+            .collect();
+        for (bit, soft) in data[100 + 17..].iter().zip(&descrambled[17..]) {
+            assert_eq!(*bit, *soft > 0.0);
+        }
+    }
 }
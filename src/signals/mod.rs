@@ -1 +1,2 @@
 pub mod lsfr;
+pub mod prbs;
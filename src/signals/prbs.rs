@@ -0,0 +1,355 @@
+use std::fmt::Display;
+
+use super::lsfr::LSFR;
+
+/// Bits compared before declaring sync
+const SYNC_BITS: usize = 64;
+/// Sync is lost if more than `LOSS_ERRORS` of `LOSS_WINDOW` bits are wrong
+const LOSS_WINDOW: usize = 256;
+const LOSS_ERRORS: usize = 64;
+/// Largest slip in bits that is told apart from an unrelated resync
+const MAX_SLIP: usize = 32;
+
+/// ITU-T O.150 test patterns x^n + x^m + 1, not inverted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prbs {
+    Prbs9,
+    Prbs15,
+    Prbs23,
+    Prbs31,
+}
+
+impl Prbs {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "prbs9" => Some(Self::Prbs9),
+            "prbs15" => Some(Self::Prbs15),
+            "prbs23" => Some(Self::Prbs23),
+            "prbs31" => Some(Self::Prbs31),
+            _ => None,
+        }
+    }
+
+    /// Register length `n`
+    pub fn width(&self) -> u32 {
+        match self {
+            Self::Prbs9 => 9,
+            Self::Prbs15 => 15,
+            Self::Prbs23 => 23,
+            Self::Prbs31 => 31,
+        }
+    }
+
+    /// Fibonacci taps of the `LSFR`
+    fn poly(&self) -> u64 {
+        let m = match self {
+            Self::Prbs9 => 5,
+            Self::Prbs15 => 14,
+            Self::Prbs23 => 18,
+            Self::Prbs31 => 28,
+        };
+        1 | (1 << (self.width() - m))
+    }
+
+    /// Generator continuing with the given `width` bits, the first one in the LSB
+    fn lsfr(&self, state: u64) -> LSFR<u64> {
+        LSFR::new(self.poly(), state).with_width(self.width())
+    }
+
+    /// Pattern generator starting from the all ones state
+    #[cfg(test)]
+    pub fn generator(&self) -> LSFR<u64> {
+        self.lsfr((1 << self.width()) - 1)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BerStats {
+    /// Bits compared while in sync
+    pub bits: usize,
+    pub errors: usize,
+    pub sync_losses: usize,
+    pub slips: usize,
+}
+
+impl BerStats {
+    pub fn ber(&self) -> f64 {
+        if self.bits == 0 {
+            return 0.0;
+        }
+        self.errors as f64 / self.bits as f64
+    }
+}
+
+impl Display for BerStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} bits, {} errors, BER {:.2e}, {} sync losses, {} slips",
+            self.bits,
+            self.errors,
+            self.ber(),
+            self.sync_losses,
+            self.slips
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BerEvent {
+    SyncAcquired {
+        bit_idx: usize,
+        inverted: bool,
+        /// Bits inserted (positive) or deleted (negative) since the sync loss
+        slip: Option<isize>,
+    },
+    SyncLost {
+        bit_idx: usize,
+    },
+    /// Emitted every `report_interval` bits in sync
+    Report(BerStats),
+}
+
+struct Sync {
+    generator: LSFR<u64>,
+    inverted: bool,
+}
+
+/// Self-synchronizing PRBS checker.
+/// The received bits are checked against the recurrence of the pattern, once
+/// `SYNC_BITS` in a row agree the generator is loaded from the received bits and
+/// runs freely. Both polarities are accepted, as the phase ambiguity may invert the bits.
+/// After a sync loss the old generator keeps running, so that a resync with a
+/// shifted phase is reported as a slip.
+pub struct BerTester {
+    prbs: Prbs,
+    report_interval: usize,
+    bit_idx: usize,
+    /// Last `width` received bits, the oldest in the LSB
+    window: u64,
+    /// Consecutive bits agreeing with the recurrence and their polarity
+    agreeing: usize,
+    polarity: bool,
+    sync: Option<Sync>,
+    /// Generator running on from the last sync
+    lost: Option<Sync>,
+    window_bits: usize,
+    window_errors: usize,
+    stats: BerStats,
+}
+
+impl BerTester {
+    pub fn new(prbs: Prbs) -> Self {
+        Self {
+            prbs,
+            report_interval: 100_000,
+            bit_idx: 0,
+            window: 0,
+            agreeing: 0,
+            polarity: false,
+            sync: None,
+            lost: None,
+            window_bits: 0,
+            window_errors: 0,
+            stats: BerStats::default(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_report_interval(mut self, bits: usize) -> Self {
+        self.report_interval = bits;
+        self
+    }
+
+    pub fn stats(&self) -> &BerStats {
+        &self.stats
+    }
+
+    #[cfg(test)]
+    pub fn is_synced(&self) -> bool {
+        self.sync.is_some()
+    }
+
+    pub fn tick(&mut self, bit: bool) -> Option<BerEvent> {
+        let width = self.prbs.width();
+        let predicted = (self.window & self.prbs.poly()).count_ones() & 1 != 0;
+        self.window = (self.window >> 1) | ((bit as u64) << (width - 1));
+        self.bit_idx += 1;
+
+        match self.sync.as_mut() {
+            Some(sync) => {
+                let error = sync.generator.next().unwrap() ^ sync.inverted != bit;
+                self.stats.bits += 1;
+                self.stats.errors += error as usize;
+                self.window_errors += error as usize;
+                self.window_bits += 1;
+                if self.window_bits == LOSS_WINDOW {
+                    let errors = self.window_errors;
+                    self.window_bits = 0;
+                    self.window_errors = 0;
+                    if errors > LOSS_ERRORS {
+                        self.stats.sync_losses += 1;
+                        self.lost = self.sync.take();
+                        self.agreeing = 0;
+                        return Some(BerEvent::SyncLost {
+                            bit_idx: self.bit_idx,
+                        });
+                    }
+                }
+                if self.stats.bits.is_multiple_of(self.report_interval) {
+                    return Some(BerEvent::Report(self.stats));
+                }
+                None
+            }
+            None => {
+                if let Some(lost) = self.lost.as_mut() {
+                    lost.generator.next();
+                }
+                self.search(bit ^ predicted)
+            }
+        }
+    }
+
+    fn search(&mut self, syndrome: bool) -> Option<BerEvent> {
+        if self.agreeing == 0 || syndrome != self.polarity {
+            self.polarity = syndrome;
+            self.agreeing = 0;
+        }
+        self.agreeing += 1;
+        // The first `width` syndromes are computed from an incomplete window
+        if self.agreeing < SYNC_BITS + self.prbs.width() as usize {
+            return None;
+        }
+
+        let inverted = self.polarity;
+        let mask = (1u64 << self.prbs.width()) - 1;
+        let mut generator = self
+            .prbs
+            .lsfr(self.window ^ if inverted { mask } else { 0 });
+        generator
+            .by_ref()
+            .take(self.prbs.width() as usize)
+            .for_each(drop);
+        let slip = self
+            .lost
+            .take()
+            .and_then(|lost| self.slip(lost.generator.state(), generator.state()));
+        if slip.is_some_and(|slip| slip != 0) {
+            self.stats.slips += 1;
+        }
+        self.sync = Some(Sync {
+            generator,
+            inverted,
+        });
+        self.window_bits = 0;
+        self.window_errors = 0;
+        Some(BerEvent::SyncAcquired {
+            bit_idx: self.bit_idx,
+            inverted,
+            slip,
+        })
+    }
+
+    /// Offset of the new generator phase relative to the old one, `None` if the
+    /// phases are further apart than `MAX_SLIP`
+    fn slip(&self, old_state: u64, new_state: u64) -> Option<isize> {
+        let mut old = self.prbs.lsfr(old_state);
+        let mut new = self.prbs.lsfr(new_state);
+        for offset in 0..=MAX_SLIP as isize {
+            // The received stream is ahead of the old generator, bits were lost
+            if old.state() == new_state {
+                return Some(-offset);
+            }
+            if new.state() == old_state {
+                return Some(offset);
+            }
+            old.next();
+            new.next();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BerEvent, BerTester, Prbs};
+    use crate::test_utils::TestRng;
+
+    #[test]
+    fn test_prbs_recurrence() {
+        for (prbs, m) in [
+            (Prbs::Prbs9, 5),
+            (Prbs::Prbs15, 14),
+            (Prbs::Prbs23, 18),
+            (Prbs::Prbs31, 28),
+        ] {
+            let n = prbs.width() as usize;
+            let bits: Vec<bool> = prbs.generator().take(1000).collect();
+            assert!(bits[..n].iter().all(|bit| *bit));
+            for idx in n..bits.len() {
+                assert_eq!(bits[idx], bits[idx - n] ^ bits[idx - m], "{prbs:?}");
+            }
+        }
+
+        let mut prbs9 = Prbs::Prbs9.generator();
+        let ones = prbs9.by_ref().take(511).filter(|bit| *bit).count();
+        assert_eq!(ones, 256);
+        assert_eq!(prbs9.state(), 0x1FF);
+    }
+
+    #[test]
+    fn test_ber_measurement() {
+        let mut rng = TestRng::new(49);
+        let mut tester = BerTester::new(Prbs::Prbs23).with_report_interval(50_000);
+        let mut reports = 0;
+        // Random bits before the pattern starts
+        for _ in 0..500 {
+            assert_eq!(tester.tick(rng.next_bool()), None);
+        }
+        let mut errors = 0;
+        for (idx, bit) in Prbs::Prbs23.generator().take(200_000).enumerate() {
+            let error = idx >= 1000 && rng.next_u64() % 1000 < 5;
+            errors += error as usize;
+            match tester.tick(!bit ^ error) {
+                Some(BerEvent::SyncAcquired { inverted, slip, .. }) => {
+                    assert!(inverted);
+                    assert_eq!(slip, None);
+                }
+                Some(BerEvent::SyncLost { .. }) => panic!("sync lost"),
+                Some(BerEvent::Report(_)) => reports += 1,
+                None => {}
+            }
+        }
+        assert!(tester.is_synced());
+        let stats = tester.stats();
+        assert_eq!(stats.errors, errors);
+        assert!((stats.ber() - 0.005).abs() < 0.001, "{}", stats);
+        assert_eq!(reports, stats.bits / 50_000);
+        assert_eq!((stats.sync_losses, stats.slips), (0, 0));
+    }
+
+    #[test]
+    fn test_ber_slips() {
+        let mut bits: Vec<bool> = Prbs::Prbs15.generator().take(20_000).collect();
+        // One bit deleted, later two bits inserted
+        bits.remove(5000);
+        bits.insert(12_000, true);
+        bits.insert(12_000, false);
+
+        let mut tester = BerTester::new(Prbs::Prbs15);
+        let events: Vec<BerEvent> = bits.iter().filter_map(|bit| tester.tick(*bit)).collect();
+        let slips: Vec<Option<isize>> = events
+            .iter()
+            .filter_map(|event| match event {
+                BerEvent::SyncAcquired { slip, inverted, .. } => {
+                    assert!(!inverted);
+                    Some(*slip)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(slips, [None, Some(-1), Some(2)]);
+        let stats = tester.stats();
+        assert_eq!((stats.sync_losses, stats.slips), (2, 2));
+    }
+}
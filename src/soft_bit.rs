@@ -0,0 +1,222 @@
+use std::fmt::Debug;
+
+use num::complex::Complex32;
+
+use crate::constellation::Constellation;
+use crate::PT1;
+
+/// A received bit, either hard decided or as a soft value.
+///
+/// Soft values follow the sign convention of the slicer: positive values are
+/// `true` bits and the magnitude is the reliability, i.e. `LLR = ln(P(1) / P(0))`.
+pub trait SoftBit: Copy + Default + Debug {
+    fn hard(self) -> bool;
+
+    /// Flips the bit, keeping its reliability
+    fn invert(self) -> Self;
+
+    /// Log-likelihood ratio, hard bits have unit reliability
+    fn llr(self) -> f32;
+
+    /// XOR of two bits, soft values use the min-sum approximation
+    fn xor(self, other: Self) -> Self;
+
+    /// Bit with the given log-likelihood ratio, hard bits only keep the sign
+    fn from_llr(llr: f32) -> Self;
+}
+
+impl SoftBit for bool {
+    fn hard(self) -> bool {
+        self
+    }
+
+    fn invert(self) -> Self {
+        !self
+    }
+
+    fn llr(self) -> f32 {
+        if self {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+
+    fn xor(self, other: Self) -> Self {
+        self ^ other
+    }
+
+    fn from_llr(llr: f32) -> Self {
+        llr >= 0.0
+    }
+}
+
+impl SoftBit for f32 {
+    fn hard(self) -> bool {
+        self >= 0.0
+    }
+
+    fn invert(self) -> Self {
+        -self
+    }
+
+    fn llr(self) -> f32 {
+        self
+    }
+
+    fn xor(self, other: Self) -> Self {
+        let magnitude = self.abs().min(other.abs());
+        if self.hard() ^ other.hard() {
+            magnitude
+        } else {
+            -magnitude
+        }
+    }
+
+    fn from_llr(llr: f32) -> Self {
+        llr
+    }
+}
+
+/// Quantized LLR, see `quantize_llr`
+impl SoftBit for i8 {
+    fn hard(self) -> bool {
+        self >= 0
+    }
+
+    fn invert(self) -> Self {
+        self.saturating_neg()
+    }
+
+    fn llr(self) -> f32 {
+        self as f32
+    }
+
+    fn xor(self, other: Self) -> Self {
+        // A zero input still yields a bit with the sign of the hard decision
+        let magnitude = self.saturating_abs().min(other.saturating_abs()).max(1);
+        if self.hard() ^ other.hard() {
+            magnitude
+        } else {
+            -magnitude
+        }
+    }
+
+    fn from_llr(llr: f32) -> Self {
+        quantize_llr(llr, I8_NOMINAL_LLR as f32)
+    }
+}
+
+/// Steps of a quantized LLR for a noise free symbol, leaving headroom up to 127
+pub const I8_NOMINAL_LLR: i8 = 32;
+
+/// Quantizes an LLR to a symmetric `-127..=127` range, `scale` being the steps per unit LLR.
+/// Rounds away from zero with a magnitude of at least 1, so weak bits keep their sign.
+pub fn quantize_llr(llr: f32, scale: f32) -> i8 {
+    let steps = (llr * scale).round().clamp(-127.0, 127.0) as i8;
+    match steps {
+        0 if llr < 0.0 => -1,
+        0 => 1,
+        steps => steps,
+    }
+}
+
+/// Quantization scale mapping the LLR of a noise free symbol to `I8_NOMINAL_LLR` steps
+pub fn i8_llr_scale(constellation: &Constellation, noise_var: f32) -> f32 {
+    I8_NOMINAL_LLR as f32 * noise_var / constellation.min_distance_sqr()
+}
+
+/// Decision directed noise variance estimate, i.e. the smoothed
+/// squared distance of the symbols to their closest constellation point.
+#[derive(Debug)]
+pub struct NoiseVarianceEstimator {
+    pt: PT1<f32>,
+}
+
+impl NoiseVarianceEstimator {
+    pub fn from_alpha(alpha: f32) -> Self {
+        Self {
+            pt: PT1 {
+                alpha,
+                current: 1.0,
+            },
+        }
+    }
+
+    pub fn tick(&mut self, constellation: &Constellation, symbol: Complex32) -> f32 {
+        let point = constellation.points()[constellation.nearest(symbol)];
+        self.pt.tick((symbol - point).norm_sqr())
+    }
+
+    #[cfg(test)]
+    pub fn noise_variance(&self) -> f32 {
+        self.pt.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{i8_llr_scale, quantize_llr, NoiseVarianceEstimator, SoftBit, I8_NOMINAL_LLR};
+    use crate::constellation::Constellation;
+    use crate::test_utils::TestRng;
+    use num::complex::Complex32;
+
+    #[test]
+    fn test_soft_bit_conventions() {
+        assert!(0.3f32.hard());
+        assert!(!(-0.3f32).hard());
+        assert_eq!(2.5f32.invert(), -2.5);
+        assert!(!true.invert());
+        assert_eq!(i8::MIN.invert(), i8::MAX);
+        assert_eq!(false.llr(), -1.0);
+    }
+
+    #[test]
+    fn test_soft_bit_xor() {
+        assert!(true.xor(false));
+        assert_eq!(2.0f32.xor(-0.5), 0.5);
+        assert_eq!((-2.0f32).xor(-0.5), -0.5);
+        assert_eq!(100i8.xor(i8::MIN), 100);
+        assert_eq!(3i8.xor(4), -3);
+        assert_eq!(0i8.xor(5), -1);
+        assert_eq!((-5i8).xor(0), 1);
+    }
+
+    #[test]
+    fn test_quantize_llr() {
+        assert_eq!(quantize_llr(1.26, 10.0), 13);
+        assert_eq!(quantize_llr(-100.0, 10.0), -127);
+        assert_eq!(quantize_llr(100.0, 10.0), 127);
+        // Weak bits keep their sign
+        assert_eq!(quantize_llr(-0.04, 10.0), -1);
+        assert_eq!(quantize_llr(0.04, 10.0), 1);
+        assert_eq!(quantize_llr(0.0, 10.0), 1);
+        assert!(!i8::from_llr(-0.01).hard());
+
+        // A noise free BPSK symbol maps to the nominal LLR, independent of the noise estimate
+        let bpsk = Constellation::bpsk();
+        for noise_var in [0.01, 0.5] {
+            let llr = bpsk.bit_llr(Complex32::new(-1.0, 0.0), noise_var, 0);
+            assert_eq!(
+                quantize_llr(llr, i8_llr_scale(&bpsk, noise_var)),
+                -I8_NOMINAL_LLR
+            );
+        }
+    }
+
+    #[test]
+    fn test_noise_variance_estimate() {
+        const SIGMA: f32 = 0.2;
+        let qpsk = Constellation::qpsk();
+        let mut rng = TestRng::new(5);
+        let mut estimator = NoiseVarianceEstimator::from_alpha(0.001);
+
+        for _ in 0..20000 {
+            let bits = (rng.next_u64() & 3) as u8;
+            let noise = Complex32::new(rng.gaussian(), rng.gaussian()) * (SIGMA / 2f32.sqrt());
+            estimator.tick(&qpsk, qpsk.map(bits) + noise);
+        }
+        let expected = SIGMA * SIGMA;
+        assert!((estimator.noise_variance() - expected).abs() < 0.1 * expected);
+    }
+}
@@ -0,0 +1,115 @@
+use num::complex::Complex32;
+
+use crate::PT1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SquelchEvent {
+    BurstStart { sample_idx: usize },
+    BurstEnd { start_idx: usize, end_idx: usize },
+}
+
+/// Power based squelch with hysteresis.
+///
+/// The input power is smoothed by a `PT1`, the squelch opens once it rises above
+/// `open_db` and closes again when it drops below `close_db`.
+/// Thresholds are given in dB relative to a power of 1.0.
+#[derive(Debug)]
+pub struct Squelch {
+    power: PT1<f32>,
+    open_thresh: f32,
+    close_thresh: f32,
+    open: bool,
+    sample_idx: usize,
+    burst_start_idx: usize,
+}
+
+impl Squelch {
+    pub fn new(alpha: f32, open_db: f32, close_db: f32) -> Self {
+        assert!(close_db <= open_db);
+        Self {
+            power: PT1::from_alpha(alpha),
+            open_thresh: 10f32.powf(open_db / 10.0),
+            close_thresh: 10f32.powf(close_db / 10.0),
+            open: false,
+            sample_idx: 0,
+            burst_start_idx: 0,
+        }
+    }
+
+    /// Returns an event if the squelch changed state with this sample
+    pub fn tick(&mut self, sample: Complex32) -> Option<SquelchEvent> {
+        let power = self.power.tick(sample.norm_sqr());
+        let sample_idx = self.sample_idx;
+        self.sample_idx += 1;
+
+        if !self.open && power > self.open_thresh {
+            self.open = true;
+            self.burst_start_idx = sample_idx;
+            return Some(SquelchEvent::BurstStart { sample_idx });
+        }
+
+        if self.open && power < self.close_thresh {
+            self.open = false;
+            return Some(SquelchEvent::BurstEnd {
+                start_idx: self.burst_start_idx,
+                end_idx: sample_idx,
+            });
+        }
+        None
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Squelch, SquelchEvent};
+    use num::complex::Complex32;
+
+    #[test]
+    fn test_squelch_marks_burst() {
+        let mut squelch = Squelch::new(0.1, -10.0, -13.0);
+        let mut events = Vec::new();
+        for idx in 0..3000 {
+            let amplitude = if (1000..2000).contains(&idx) {
+                1.0
+            } else {
+                0.01
+            };
+            if let Some(event) = squelch.tick(Complex32::new(0.0, amplitude)) {
+                events.push(event);
+            }
+        }
+
+        assert_eq!(events.len(), 2);
+        match events[0] {
+            SquelchEvent::BurstStart { sample_idx } => assert!((1000..1010).contains(&sample_idx)),
+            _ => panic!("expected burst start, got {:?}", events[0]),
+        }
+        match events[1] {
+            SquelchEvent::BurstEnd { start_idx, end_idx } => {
+                assert!((1000..1010).contains(&start_idx));
+                assert!((2000..2050).contains(&end_idx));
+            }
+            _ => panic!("expected burst end, got {:?}", events[1]),
+        }
+        assert!(!squelch.is_open());
+    }
+
+    #[test]
+    fn test_squelch_hysteresis() {
+        let mut squelch = Squelch::new(1.0, -10.0, -20.0);
+        assert!(squelch.tick(Complex32::new(1.0, 0.0)).is_some());
+
+        // Fluctuating between both thresholds keeps the squelch open
+        for idx in 0..100 {
+            let amplitude = if idx % 2 == 0 { 0.15 } else { 0.3 };
+            assert_eq!(squelch.tick(Complex32::new(amplitude, 0.0)), None);
+        }
+        assert!(squelch.is_open());
+        assert!(squelch.tick(Complex32::new(0.05, 0.0)).is_some());
+        assert!(!squelch.is_open());
+    }
+}
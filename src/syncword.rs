@@ -1,11 +1,15 @@
 use crate::ringbuffer::RingBuffer;
+use crate::soft_bit::SoftBit;
 
+/// Bitwise reference for `PackedSyncwordScan`, kept for the tests and the timing harness
+#[cfg(test)]
 pub struct SyncwordScanXCorr<S, const N: usize> {
     syncword: [S; N],
     error_hist: RingBuffer<u16, N>,
     error_thresh: u16,
 }
 
+#[cfg(test)]
 impl<S, const N: usize> SyncwordScanXCorr<S, N>
 where
     S: PartialEq,
@@ -30,6 +34,11 @@ where
         self.error_hist[0usize] <= self.error_thresh
     }
 
+    /// Bit errors of the syncword candidate that ended with the last symbol
+    pub fn errors(&self) -> u16 {
+        self.error_hist[0usize]
+    }
+
     pub fn reset(&mut self) -> bool {
         for idx in 0..N {
             self.error_hist[idx] = self.error_thresh + 1;
@@ -38,43 +47,977 @@ where
     }
 }
 
-pub struct SyncwordPacketizer<S, const SyncN: usize, const PacketN: usize> {
-    scan: SyncwordScanXCorr<S, SyncN>,
+/// Unpacks the `N` least significant bits of `syncword`, MSB (first transmitted bit) first
+pub const fn syncword_bits<const N: usize>(syncword: u64) -> [bool; N] {
+    let mut bits = [false; N];
+    let mut idx = 0;
+    while idx < N {
+        bits[idx] = (syncword >> (N - 1 - idx)) & 1 != 0;
+        idx += 1;
+    }
+    bits
+}
+
+/// Carrier phase ambiguities the demapped bits may be subject to,
+/// assuming the Gray mapping of `Constellation::bpsk` and `Constellation::qpsk`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhaseAmbiguity {
+    None,
+    /// 0° and 180°
+    Bpsk,
+    /// Rotations by multiples of 90°
+    Qpsk,
+    /// Rotations by multiples of 90°, with and without swapped I/Q (conjugated) spectrum
+    #[allow(dead_code)]
+    QpskConjugate,
+}
+
+impl PhaseAmbiguity {
+    pub fn variants(self) -> Vec<SyncwordVariant> {
+        let (bits_per_symbol, rotations, conjugates): (usize, &[u16], &[bool]) = match self {
+            Self::None => (1, &[0], &[false]),
+            Self::Bpsk => (1, &[0, 180], &[false]),
+            Self::Qpsk => (2, &[0, 90, 180, 270], &[false]),
+            Self::QpskConjugate => (2, &[0, 90, 180, 270], &[false, true]),
+        };
+        conjugates
+            .iter()
+            .flat_map(|conjugate| {
+                rotations.iter().map(|rotation_deg| SyncwordVariant {
+                    rotation_deg: *rotation_deg,
+                    conjugate: *conjugate,
+                    bits_per_symbol,
+                })
+            })
+            .collect()
+    }
+}
+
+/// One phase hypothesis: the received constellation is the transmitted one,
+/// conjugated (if `conjugate`) and then rotated by `rotation_deg`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncwordVariant {
+    pub rotation_deg: u16,
+    pub conjugate: bool,
+    bits_per_symbol: usize,
+}
+
+impl SyncwordVariant {
+    pub fn bits_per_symbol(&self) -> usize {
+        self.bits_per_symbol
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.rotation_deg == 0 && !self.conjugate
+    }
+
+    /// Turns transmitted bits into the bits received under this hypothesis
+    pub fn apply<S: SoftBit>(&self, bits: &mut [S]) {
+        if self.bits_per_symbol == 1 {
+            if self.rotation_deg == 180 {
+                bits.iter_mut().for_each(|bit| *bit = bit.invert());
+            }
+            return;
+        }
+        for symbol in bits.chunks_exact_mut(2) {
+            if self.conjugate {
+                symbol[1] = symbol[1].invert();
+            }
+            for _ in 0..self.rotation_deg / 90 {
+                // +90°: (re, im) -> (-im, re)
+                let msb = symbol[0];
+                symbol[0] = symbol[1].invert();
+                symbol[1] = msb;
+            }
+        }
+    }
+
+    /// Undoes `apply`, i.e. recovers the transmitted bits
+    pub fn correct<S: SoftBit>(&self, bits: &mut [S]) {
+        if self.bits_per_symbol == 1 {
+            self.apply(bits);
+            return;
+        }
+        for symbol in bits.chunks_exact_mut(2) {
+            for _ in 0..self.rotation_deg / 90 {
+                let lsb = symbol[1];
+                symbol[1] = symbol[0].invert();
+                symbol[0] = lsb;
+            }
+            if self.conjugate {
+                symbol[1] = symbol[1].invert();
+            }
+        }
+    }
+}
+
+/// Syncword detection stage of the `SyncwordPacketizer`
+pub trait SyncwordDetector<S> {
+    /// Returns the matched phase variant when the syncword has just been received
+    fn detect(&mut self, symbol_in: S) -> Option<SyncwordVariant>;
+
+    /// Quality of the last detection, higher is better
+    fn match_quality(&self) -> f32 {
+        0.0
+    }
+
+    fn reset(&mut self);
+}
+
+impl<S, D: SyncwordDetector<S> + ?Sized> SyncwordDetector<S> for Box<D> {
+    fn detect(&mut self, symbol_in: S) -> Option<SyncwordVariant> {
+        (**self).detect(symbol_in)
+    }
+
+    fn match_quality(&self) -> f32 {
+        (**self).match_quality()
+    }
+
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+}
+
+/// Searches for all phase variants of a syncword at once, bitwise reference for `PackedSyncwordScan`
+#[cfg(test)]
+pub struct AmbiguousSyncwordScan<const N: usize> {
+    scans: Vec<(SyncwordVariant, SyncwordScanXCorr<bool, N>)>,
+    last_errors: u16,
+}
+
+#[cfg(test)]
+impl<const N: usize> AmbiguousSyncwordScan<N> {
+    pub fn new(syncword: [bool; N], error_thresh: u16, ambiguity: PhaseAmbiguity) -> Self {
+        let scans = ambiguity
+            .variants()
+            .into_iter()
+            .map(|variant| {
+                let mut received = syncword;
+                variant.apply(&mut received);
+                (variant, SyncwordScanXCorr::new(received, error_thresh))
+            })
+            .collect();
+        Self {
+            scans,
+            last_errors: 0,
+        }
+    }
+
+    /// Returns the matching variant, the identity wins if several match
+    pub fn tick(&mut self, symbol_in: bool) -> Option<SyncwordVariant> {
+        let mut matched = None;
+        for (variant, scan) in self.scans.iter_mut() {
+            if scan.tick(symbol_in) && matched.is_none() {
+                matched = Some(*variant);
+                self.last_errors = scan.errors();
+            }
+        }
+        matched
+    }
+
+    pub fn reset(&mut self) {
+        for (_, scan) in self.scans.iter_mut() {
+            scan.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+impl<S: SoftBit, const N: usize> SyncwordDetector<S> for AmbiguousSyncwordScan<N> {
+    fn detect(&mut self, symbol_in: S) -> Option<SyncwordVariant> {
+        self.tick(symbol_in.hard())
+    }
+
+    fn match_quality(&self) -> f32 {
+        -(self.last_errors as f32)
+    }
+
+    fn reset(&mut self) {
+        AmbiguousSyncwordScan::reset(self)
+    }
+}
+
+/// Hard decision syncword search on a bit packed shift register, the Hamming
+/// distance to each phase variant costs one XOR and `count_ones` per bit and
+/// 64 bit word. Syncwords longer than 64 bits span several words, the first
+/// word holds the oldest bits.
+#[derive(Debug)]
+pub struct PackedSyncwordScan {
+    patterns: Vec<(SyncwordVariant, Vec<u64>)>,
+    /// Valid bits of the first word
+    mask: u64,
+    len: u32,
+    register: Vec<u64>,
+    bits_seen: u32,
+    error_thresh: u32,
+    last_errors: u32,
+}
+
+/// Bits of a syncword given as hex digits (optionally prefixed with `0x`),
+/// 4 bits per digit, MSB first
+pub fn hex_bits(hex: &str) -> Option<Vec<bool>> {
+    let digits = hex.strip_prefix("0x").unwrap_or(hex);
+    if digits.is_empty() {
+        return None;
+    }
+    let mut bits = Vec::with_capacity(4 * digits.len());
+    for digit in digits.chars() {
+        let value = digit.to_digit(16)?;
+        bits.extend((0..4).rev().map(|idx| (value >> idx) & 1 != 0));
+    }
+    Some(bits)
+}
+
+/// Packs bits MSB first into 64 bit words, the first word is padded at its top
+fn pack_bits(bits: &[bool]) -> Vec<u64> {
+    let padding = bits.len().next_multiple_of(64) - bits.len();
+    let padded: Vec<bool> = std::iter::repeat_n(false, padding)
+        .chain(bits.iter().copied())
+        .collect();
+    padded
+        .chunks(64)
+        .map(|word| word.iter().fold(0u64, |acc, bit| (acc << 1) | *bit as u64))
+        .collect()
+}
+
+impl PackedSyncwordScan {
+    /// `syncword` holds `len` bits, the first transmitted bit is the MSB
+    pub fn new(syncword: u64, len: u32, error_thresh: u32, ambiguity: PhaseAmbiguity) -> Self {
+        assert!(
+            (1..=64).contains(&len),
+            "syncword length must be 1..=64 bits"
+        );
+        let bits: Vec<bool> = (0..len)
+            .map(|idx| (syncword >> (len - 1 - idx)) & 1 != 0)
+            .collect();
+        Self::from_bits(&bits, error_thresh, ambiguity)
+    }
+
+    /// Syncword of any length, in transmission order
+    pub fn from_bits(syncword: &[bool], error_thresh: u32, ambiguity: PhaseAmbiguity) -> Self {
+        assert!(!syncword.is_empty(), "empty syncword");
+        let len = syncword.len() as u32;
+        let patterns: Vec<(SyncwordVariant, Vec<u64>)> = ambiguity
+            .variants()
+            .into_iter()
+            .map(|variant| {
+                let mut received = syncword.to_vec();
+                variant.apply(&mut received);
+                (variant, pack_bits(&received))
+            })
+            .collect();
+        Self {
+            mask: u64::MAX >> ((64 - len % 64) % 64),
+            len,
+            register: vec![0; patterns[0].1.len()],
+            patterns,
+            bits_seen: 0,
+            error_thresh,
+            last_errors: 0,
+        }
+    }
+
+    /// Syncword given as hex digits, see `hex_bits`
+    #[cfg(test)]
+    pub fn parse(hex: &str, error_thresh: u32, ambiguity: PhaseAmbiguity) -> Option<Self> {
+        Some(Self::from_bits(&hex_bits(hex)?, error_thresh, ambiguity))
+    }
+
+    #[cfg(test)]
+    pub fn syncword_len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn tick(&mut self, symbol_in: bool) -> Option<SyncwordVariant> {
+        let words = self.register.len();
+        for idx in 0..words {
+            let carry = match self.register.get(idx + 1) {
+                Some(next) => next >> 63,
+                None => symbol_in as u64,
+            };
+            self.register[idx] = (self.register[idx] << 1) | carry;
+        }
+        self.register[0] &= self.mask;
+        self.bits_seen = (self.bits_seen + 1).min(self.len);
+        if self.bits_seen < self.len {
+            return None;
+        }
+
+        let (variant, errors) = self
+            .patterns
+            .iter()
+            .map(|(variant, pattern)| {
+                let errors = (self.register.iter().zip(pattern))
+                    .map(|(received, pattern)| (received ^ pattern).count_ones())
+                    .sum();
+                (*variant, errors)
+            })
+            .find(|(_, errors)| *errors <= self.error_thresh)?;
+        self.last_errors = errors;
+        Some(variant)
+    }
+
+    pub fn reset(&mut self) {
+        self.register.fill(0);
+        self.bits_seen = 0;
+    }
+}
+
+impl<S: SoftBit> SyncwordDetector<S> for PackedSyncwordScan {
+    fn detect(&mut self, symbol_in: S) -> Option<SyncwordVariant> {
+        self.tick(symbol_in.hard())
+    }
+
+    fn match_quality(&self) -> f32 {
+        -(self.last_errors as f32)
+    }
+
+    fn reset(&mut self) {
+        PackedSyncwordScan::reset(self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyncwordPeak {
+    pub variant: SyncwordVariant,
+    /// Correlation of the last `N` LLRs with the (±1) syncword
+    pub correlation: f32,
+    /// Correlation in standard deviations of the correlator output for random data
+    pub normalized: f32,
+}
+
+/// Soft decision syncword correlator with a CFAR threshold.
+///
+/// For random data the correlation of `N` LLRs with a ±1 syncword has a variance
+/// of `N * E[llr²]`. The mean LLR power is tracked over a longer window, so the
+/// threshold (in standard deviations) keeps a constant false alarm rate
+/// independent of the signal level and the LLR scaling.
+/// The estimate is floored by the power within the correlation window.
+///
+/// Every bit costs `N` multiply-adds per phase variant, i.e. O(N · variants),
+/// only the window power is kept as a running sum. `PackedSyncwordScan` is the
+/// cheaper choice for hard decisions.
+pub struct SoftSyncwordCorrelator<const N: usize> {
+    patterns: Vec<(SyncwordVariant, [f32; N])>,
+    history: RingBuffer<f32, N>,
+    /// Running sum of the squared LLRs in `history`
+    window_energy: f64,
+    power: f32,
+    alpha: f32,
+    bits_seen: usize,
+    history_len: usize,
+    threshold: f32,
+    last_peak: Option<SyncwordPeak>,
+}
+
+impl<const N: usize> SoftSyncwordCorrelator<N> {
+    /// `threshold` in standard deviations, `sqrt(N)` is reached by an error free syncword
+    pub fn new(syncword: [bool; N], threshold: f32, ambiguity: PhaseAmbiguity) -> Self {
+        let patterns = ambiguity
+            .variants()
+            .into_iter()
+            .map(|variant| {
+                let mut pattern = syncword.map(|bit| bit.llr());
+                variant.apply(&mut pattern);
+                (variant, pattern)
+            })
+            .collect();
+        Self {
+            patterns,
+            history: RingBuffer::default(),
+            window_energy: 0.0,
+            power: 0.0,
+            alpha: 1.0 / (4 * N) as f32,
+            bits_seen: 0,
+            history_len: 0,
+            threshold,
+            last_peak: None,
+        }
+    }
+
+    pub fn tick<S: SoftBit>(&mut self, symbol_in: S) -> Option<SyncwordPeak> {
+        let llr = symbol_in.llr();
+        let leaving = self.history[0usize];
+        self.history.push(llr);
+        if self.history_len == N {
+            self.window_energy -= (leaving * leaving) as f64;
+        }
+        self.window_energy += (llr * llr) as f64;
+        // Running mean until the averaging window is filled
+        self.bits_seen += 1;
+        let alpha = self.alpha.max(1.0 / self.bits_seen as f32);
+        self.power += (llr * llr - self.power) * alpha;
+        self.history_len = (self.history_len + 1).min(N);
+        if self.history_len < N {
+            return None;
+        }
+        // Resum once per window, so the rounding errors of the running sum cannot accumulate
+        if self.bits_seen.is_multiple_of(N) {
+            self.window_energy = (0..N).map(|idx| self.history[idx].powi(2) as f64).sum();
+        }
+
+        // The window's own power guards against a stale estimate, e.g. after a
+        // packet during which the detector was not fed
+        let window_power = self.window_energy.max(0.0) as f32 / N as f32;
+        let sigma = (N as f32 * self.power.max(window_power))
+            .sqrt()
+            .max(f32::EPSILON);
+        let (variant, correlation) = self
+            .patterns
+            .iter()
+            .map(|(variant, pattern)| {
+                let correlation = (0..N).map(|idx| pattern[idx] * self.history[idx]).sum();
+                (*variant, correlation)
+            })
+            .max_by(|(_, a): &(SyncwordVariant, f32), (_, b)| a.total_cmp(b))?;
+
+        let peak = SyncwordPeak {
+            variant,
+            correlation,
+            normalized: correlation / sigma,
+        };
+        (peak.normalized >= self.threshold).then(|| {
+            self.last_peak = Some(peak);
+            peak
+        })
+    }
+
+    /// The last detected peak
+    #[cfg(test)]
+    pub fn last_peak(&self) -> Option<SyncwordPeak> {
+        self.last_peak
+    }
+
+    /// Restarts the search, the noise estimate is kept
+    pub fn reset(&mut self) {
+        self.history_len = 0;
+        self.window_energy = 0.0;
+    }
+}
+
+impl<S: SoftBit, const N: usize> SyncwordDetector<S> for SoftSyncwordCorrelator<N> {
+    fn detect(&mut self, symbol_in: S) -> Option<SyncwordVariant> {
+        self.tick(symbol_in).map(|peak| peak.variant)
+    }
+
+    fn match_quality(&self) -> f32 {
+        self.last_peak.map_or(0.0, |peak| peak.normalized)
+    }
+
+    fn reset(&mut self) {
+        SoftSyncwordCorrelator::reset(self)
+    }
+}
+
+/// Turns a bit stream into the (phase corrected) packets following each syncword
+pub trait Packetizer<S> {
+    fn tick(&mut self, symbol_in: S) -> Option<&mut [S]>;
+
+    /// Phase variant of the syncword that started the last packet
+    fn matched_variant(&self) -> Option<SyncwordVariant>;
+
+    /// Discards a partially collected packet and restarts the syncword search
+    fn reset(&mut self);
+
+    /// Packets dropped because the length read from their header was invalid,
+    /// `None` for packetizers without a length field
+    fn invalid_lengths(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// What the `SyncwordPacketizer` does with syncwords found while a packet is collected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlapPolicy {
+    /// Syncword search pauses until the packet is complete
+    Blocking,
+    /// Keeps searching and restarts the packet on a better matching syncword
+    RestartOnStronger,
+    /// Collects up to `max_candidates` overlapping packets, all of them are returned
+    /// and later CRC/FEC stages have to pick the valid one
+    Parallel { max_candidates: usize },
+}
 
-    packet_active: bool,
-    packet_buffer: [S; PacketN],
-    packet_buffer_idx: usize,
+impl OverlapPolicy {
+    /// Parses `block`, `restart` or `parallel[:<max candidates>]`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.split_once(':') {
+            None if name == "block" => Some(Self::Blocking),
+            None if name == "restart" => Some(Self::RestartOnStronger),
+            None if name == "parallel" => Some(Self::Parallel { max_candidates: 4 }),
+            Some(("parallel", max)) => Some(Self::Parallel {
+                max_candidates: max.parse().ok().filter(|max| *max > 0)?,
+            }),
+            _ => None,
+        }
+    }
 }
 
-impl<S, const SyncN: usize, const PacketN: usize> SyncwordPacketizer<S, SyncN, PacketN>
+struct Candidate<S> {
+    variant: SyncwordVariant,
+    quality: f32,
+    bits: Vec<S>,
+}
+
+/// Collects `packet_len` bits after each syncword found by the detector.
+/// The syncword is usually searched on the hard decisions, the packet keeps the (soft) input bits.
+/// Packets found through a phase rotated syncword are corrected before being returned.
+pub struct SyncwordPacketizer<S, D> {
+    scan: D,
+    policy: OverlapPolicy,
+    packet_len: usize,
+    variant: Option<SyncwordVariant>,
+
+    /// Packets being collected, oldest first
+    candidates: Vec<Candidate<S>>,
+    packet_buffer: Vec<S>,
+}
+
+impl<S, D> SyncwordPacketizer<S, D>
 where
-    S: PartialEq + Default + Copy,
+    S: SoftBit,
+    D: SyncwordDetector<S>,
 {
-    pub fn new(syncword: [S; SyncN], error_thresh: u16) -> Self {
+    pub fn new(detector: D, packet_len: usize) -> Self {
         Self {
-            scan: SyncwordScanXCorr::new(syncword, error_thresh),
-            packet_active: false,
-            packet_buffer: [S::default(); PacketN],
-            packet_buffer_idx: 0,
+            scan: detector,
+            policy: OverlapPolicy::Blocking,
+            packet_len,
+            variant: None,
+            candidates: Vec::new(),
+            packet_buffer: Vec::with_capacity(packet_len),
         }
     }
 
+    pub fn with_overlap_policy(self, policy: OverlapPolicy) -> Self {
+        Self { policy, ..self }
+    }
+
     pub fn tick(&mut self, symbol_in: S) -> Option<&mut [S]> {
-        if self.packet_active {
-            self.packet_buffer[self.packet_buffer_idx] = symbol_in;
-            self.packet_buffer_idx += 1;
-            if self.packet_buffer_idx == PacketN {
-                self.packet_active = false;
-                self.packet_buffer_idx = 0;
-                return Some(self.packet_buffer.as_mut_slice());
+        let scanning = self.policy != OverlapPolicy::Blocking || self.candidates.is_empty();
+        let detection = match scanning {
+            true => self.scan.detect(symbol_in),
+            false => None,
+        };
+
+        for candidate in self.candidates.iter_mut() {
+            candidate.bits.push(symbol_in);
+        }
+        let completed = match self.candidates.first() {
+            Some(candidate) if candidate.bits.len() == self.packet_len => {
+                Some(self.candidates.remove(0))
             }
-            return None;
+            _ => None,
+        };
+
+        if let Some(variant) = detection {
+            self.start_candidate(variant);
         }
 
-        if self.scan.tick(symbol_in) {
-            self.packet_active = true;
-            self.scan.reset();
+        let mut candidate = completed?;
+        candidate.variant.correct(&mut candidate.bits);
+        self.variant = Some(candidate.variant);
+        self.packet_buffer = candidate.bits;
+        Some(self.packet_buffer.as_mut_slice())
+    }
+
+    fn start_candidate(&mut self, variant: SyncwordVariant) {
+        let quality = self.scan.match_quality();
+        match self.policy {
+            OverlapPolicy::Blocking => self.scan.reset(),
+            OverlapPolicy::RestartOnStronger => {
+                if self.candidates.iter().any(|other| other.quality >= quality) {
+                    return;
+                }
+                self.candidates.clear();
+            }
+            OverlapPolicy::Parallel { max_candidates } => {
+                if self.candidates.len() >= max_candidates {
+                    // Replace the weakest candidate, if this one is better
+                    let weakest = self
+                        .candidates
+                        .iter()
+                        .enumerate()
+                        .min_by(|(_, a), (_, b)| a.quality.total_cmp(&b.quality))
+                        .map(|(idx, candidate)| (idx, candidate.quality));
+                    match weakest {
+                        Some((idx, weakest_quality)) if weakest_quality < quality => {
+                            self.candidates.remove(idx);
+                        }
+                        _ => return,
+                    }
+                }
+            }
         }
-        None
+        self.candidates.push(Candidate {
+            variant,
+            quality,
+            bits: Vec::with_capacity(self.packet_len),
+        });
+    }
+
+    /// Phase variant of the syncword that started the last packet
+    pub fn matched_variant(&self) -> Option<SyncwordVariant> {
+        self.variant
+    }
+
+    /// Discards partially collected packets and restarts the syncword search
+    pub fn reset(&mut self) {
+        self.variant = None;
+        self.candidates.clear();
+        self.scan.reset();
+    }
+}
+
+impl<S, D> Packetizer<S> for SyncwordPacketizer<S, D>
+where
+    S: SoftBit,
+    D: SyncwordDetector<S>,
+{
+    fn tick(&mut self, symbol_in: S) -> Option<&mut [S]> {
+        SyncwordPacketizer::tick(self, symbol_in)
+    }
+
+    fn matched_variant(&self) -> Option<SyncwordVariant> {
+        SyncwordPacketizer::matched_variant(self)
+    }
+
+    fn reset(&mut self) {
+        SyncwordPacketizer::reset(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        hex_bits, syncword_bits, AmbiguousSyncwordScan, OverlapPolicy, PackedSyncwordScan,
+        PhaseAmbiguity, SoftSyncwordCorrelator, SyncwordPacketizer, SyncwordScanXCorr,
+    };
+    use crate::constellation::Constellation;
+    use crate::test_utils::TestRng;
+    use num::complex::Complex32;
+    use std::hint::black_box;
+    use std::time::{Duration, Instant};
+
+    const SYNC: [bool; 8] = [true, true, false, true, false, false, true, false];
+
+    #[test]
+    fn test_variants_match_qpsk_rotations() {
+        let constellation = Constellation::qpsk();
+        for variant in PhaseAmbiguity::QpskConjugate.variants() {
+            let rotation = Complex32::from_polar(1.0, (variant.rotation_deg as f32).to_radians());
+            for symbol in 0..4u8 {
+                let mut point = constellation.map(symbol);
+                if variant.conjugate {
+                    point = point.conj();
+                }
+                let received = constellation.decide(point * rotation);
+
+                let mut bits: Vec<bool> = constellation.symbol_bits(symbol).collect();
+                variant.apply(&mut bits);
+                assert_eq!(
+                    bits,
+                    constellation.symbol_bits(received).collect::<Vec<_>>()
+                );
+                variant.correct(&mut bits);
+                assert_eq!(bits, constellation.symbol_bits(symbol).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    #[test]
+    fn test_packetizer_corrects_rotated_packets() {
+        let mut rng = TestRng::new(37);
+        let payload: Vec<bool> = (0..64).map(|_| rng.next_bool()).collect();
+        for ambiguity in [PhaseAmbiguity::Bpsk, PhaseAmbiguity::QpskConjugate] {
+            for variant in ambiguity.variants() {
+                let mut bits: Vec<bool> = [false; 10].into_iter().chain(SYNC).collect();
+                bits.extend(payload.iter());
+                variant.apply(&mut bits);
+
+                let mut packetizer =
+                    SyncwordPacketizer::new(AmbiguousSyncwordScan::new(SYNC, 0, ambiguity), 64);
+                let packets: Vec<Vec<bool>> = bits
+                    .iter()
+                    .filter_map(|bit| packetizer.tick(*bit).map(|packet| packet.to_vec()))
+                    .collect();
+                assert_eq!(packets, vec![payload.clone()], "{:?}", variant);
+                assert_eq!(packetizer.matched_variant(), Some(variant));
+            }
+        }
+
+        // Without ambiguity resolution the inverted syncword is not found
+        let scan = AmbiguousSyncwordScan::new(SYNC, 0, PhaseAmbiguity::None);
+        let mut packetizer = SyncwordPacketizer::new(scan, 64);
+        let inverted = SYNC.iter().chain(payload.iter()).map(|bit| !bit);
+        assert_eq!(
+            inverted
+                .filter_map(|bit| packetizer.tick(bit).map(|_| ()))
+                .count(),
+            0
+        );
+    }
+
+    /// Noisy LLRs of random bits followed by the syncword
+    fn llrs_with_syncword(rng: &mut TestRng, syncword: &[bool], offset: usize) -> Vec<f32> {
+        let mut bits: Vec<bool> = (0..offset).map(|_| rng.next_bool()).collect();
+        bits.extend(syncword);
+        bits.extend((0..16).map(|_| rng.next_bool()));
+        bits.iter()
+            .map(|bit| if *bit { 1.0 } else { -1.0 } + 0.4 * rng.gaussian())
+            .collect()
+    }
+
+    #[test]
+    fn test_soft_correlator_tolerates_unreliable_errors() {
+        let mut rng = TestRng::new(38);
+        let syncword = crate::SYNCWORD;
+        let mut llrs = llrs_with_syncword(&mut rng, &syncword, 200);
+        // Four weak bit errors within the syncword, too many for the hard scanner
+        for idx in [3, 11, 17, 29] {
+            llrs[200 + idx] = if syncword[idx] { -0.1 } else { 0.1 };
+        }
+
+        let mut scan = AmbiguousSyncwordScan::new(syncword, 1, PhaseAmbiguity::None);
+        assert!(llrs.iter().all(|llr| scan.tick(*llr >= 0.0).is_none()));
+
+        for scale in [1.0, 100.0] {
+            let mut correlator = SoftSyncwordCorrelator::new(syncword, 4.0, PhaseAmbiguity::Bpsk);
+            let detections: Vec<(usize, f32)> = llrs
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, llr)| {
+                    correlator
+                        .tick(llr * scale)
+                        .map(|peak| (idx, peak.normalized))
+                })
+                .collect();
+            assert_eq!(detections.len(), 1);
+            assert_eq!(detections[0].0, 200 + syncword.len() - 1);
+            assert!(detections[0].1 > 4.0 && detections[0].1 < 32f32.sqrt());
+            assert!(correlator.last_peak().unwrap().variant.is_identity());
+        }
+    }
+
+    #[test]
+    fn test_soft_correlator_false_alarms() {
+        let mut rng = TestRng::new(39);
+        let mut correlator =
+            SoftSyncwordCorrelator::new(crate::SYNCWORD, 4.5, PhaseAmbiguity::Qpsk);
+        let false_alarms = (0..50000)
+            .filter(|_| {
+                let llr = if rng.next_bool() { 2.0 } else { -2.0 } + rng.gaussian();
+                correlator.tick(llr).is_some()
+            })
+            .count();
+        assert!(false_alarms <= 2, "{} false alarms", false_alarms);
+    }
+
+    #[test]
+    fn test_soft_correlator_window_energy() {
+        let mut rng = TestRng::new(38);
+        let mut correlator =
+            SoftSyncwordCorrelator::new(crate::SYNCWORD, 4.0, PhaseAmbiguity::None);
+        for idx in 0..1000 {
+            // Large LLRs entering and leaving the window
+            let scale = if idx % 97 < 5 { 1000.0 } else { 1.0 };
+            correlator.tick(scale * rng.gaussian());
+            if idx == 500 {
+                correlator.reset();
+            }
+            let valid = correlator.history_len;
+            let expected: f64 = (32 - valid..32)
+                .map(|idx| correlator.history[idx].powi(2) as f64)
+                .sum();
+            let error = (correlator.window_energy - expected).abs();
+            assert!(error <= 1e-3 * expected.max(1.0), "{idx}: {error}");
+        }
+    }
+
+    #[test]
+    fn test_overlap_policies() {
+        let mut rng = TestRng::new(41);
+        let payload: Vec<bool> = (0..64).map(|_| rng.next_bool()).collect();
+        // Syncword with one bit error, the real one follows three bits after it
+        let mut bits: Vec<bool> = (0..40).map(|_| rng.next_bool()).collect();
+        let mut false_sync = syncword_bits::<32>(0x1ACFFC1D);
+        false_sync[7] ^= true;
+        bits.extend(false_sync);
+        bits.extend([true, false, true]);
+        bits.extend(syncword_bits::<32>(0x1ACFFC1D));
+        bits.extend(payload.iter());
+        bits.extend((0..64).map(|_| rng.next_bool()));
+
+        let packets = |policy: OverlapPolicy| {
+            let detector = PackedSyncwordScan::new(0x1ACFFC1D, 32, 1, PhaseAmbiguity::None);
+            let mut packetizer = SyncwordPacketizer::new(detector, 64).with_overlap_policy(policy);
+            bits.iter()
+                .filter_map(|bit| packetizer.tick(*bit).map(|packet| packet.to_vec()))
+                .collect::<Vec<_>>()
+        };
+
+        let blocked = packets(OverlapPolicy::Blocking);
+        assert_eq!(blocked.len(), 1);
+        assert_ne!(blocked[0], payload);
+        assert_eq!(
+            packets(OverlapPolicy::RestartOnStronger),
+            vec![payload.clone()]
+        );
+        let parallel = packets(OverlapPolicy::Parallel { max_candidates: 2 });
+        assert_eq!(parallel.len(), 2);
+        assert_eq!(parallel[0], blocked[0]);
+        assert_eq!(parallel[1], payload);
+
+        assert_eq!(
+            OverlapPolicy::parse("parallel:3"),
+            Some(OverlapPolicy::Parallel { max_candidates: 3 })
+        );
+        assert_eq!(OverlapPolicy::parse("parallel:0"), None);
+    }
+
+    /// Random bits with copies of `syncword` that have 0..3 bit errors
+    fn bits_with_syncwords(rng: &mut TestRng, syncword: &[bool]) -> Vec<bool> {
+        let mut bits = Vec::new();
+        for errors in 0..4 {
+            bits.extend((0..300).map(|_| rng.next_bool()));
+            let start = bits.len();
+            bits.extend(syncword);
+            for idx in 0..errors {
+                bits[start + 5 * idx + 2] ^= true;
+            }
+        }
+        bits
+    }
+
+    #[test]
+    fn test_packed_scan_matches_xcorr() {
+        let mut rng = TestRng::new(39);
+        for ambiguity in [PhaseAmbiguity::None, PhaseAmbiguity::Qpsk] {
+            let mut bits = bits_with_syncwords(&mut rng, &crate::SYNCWORD);
+            PhaseAmbiguity::Qpsk.variants()[1].apply(&mut bits);
+
+            let mut packed = PackedSyncwordScan::new(0x1ACFFC1D, 32, 2, ambiguity);
+            let mut xcorr = AmbiguousSyncwordScan::new(crate::SYNCWORD, 2, ambiguity);
+            let detections: Vec<_> = bits
+                .iter()
+                .map(|bit| (packed.tick(*bit), xcorr.tick(*bit)))
+                .collect();
+            assert!(detections.iter().all(|(a, b)| a == b));
+            let expected = if ambiguity == PhaseAmbiguity::None {
+                0
+            } else {
+                3
+            };
+            assert_eq!(
+                detections.iter().filter(|(a, _)| a.is_some()).count(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_packed_scan_long_syncwords() {
+        let mut rng = TestRng::new(46);
+        // Rate 1/6 turbo ASM, three words
+        let syncword = hex_bits("25D5C0CE8990F6C9461BF79CDA2A3F31766F0936B9E40863").unwrap();
+        let array: [bool; 192] = syncword.as_slice().try_into().unwrap();
+        let mut bits = bits_with_syncwords(&mut rng, &syncword);
+        PhaseAmbiguity::Qpsk.variants()[2].apply(&mut bits);
+
+        let mut packed = PackedSyncwordScan::from_bits(&syncword, 2, PhaseAmbiguity::Qpsk);
+        let mut xcorr = AmbiguousSyncwordScan::new(array, 2, PhaseAmbiguity::Qpsk);
+        let detections: Vec<(usize, u16)> = bits
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, bit)| {
+                let detection = packed.tick(*bit);
+                assert_eq!(detection, xcorr.tick(*bit));
+                detection.map(|variant| (idx, variant.rotation_deg))
+            })
+            .collect();
+        let ends = |count: usize| (1..=count).map(|idx| idx * (300 + 192) - 1);
+        assert_eq!(
+            detections,
+            ends(3).map(|idx| (idx, 180)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_packed_scan_hex_syncwords() {
+        assert_eq!(
+            syncword_bits::<8>(0xA5),
+            [true, false, true, false, false, true, false, true]
+        );
+        assert!(PackedSyncwordScan::parse("", 0, PhaseAmbiguity::None).is_none());
+        assert!(PackedSyncwordScan::parse("0x1ACFFC1G", 0, PhaseAmbiguity::None).is_none());
+
+        // 64 bit syncword, e.g. the CCSDS ASM for rate 1/2 turbo codes
+        let mut scan =
+            PackedSyncwordScan::parse("0x034776C7272895B0", 0, PhaseAmbiguity::Bpsk).unwrap();
+        assert_eq!(scan.syncword_len(), 64);
+        let mut bits = vec![true; 10];
+        bits.extend(syncword_bits::<64>(0x034776C7272895B0).map(|bit| !bit));
+        let detections: Vec<_> = bits
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, bit)| scan.tick(*bit).map(|variant| (idx, variant.rotation_deg)))
+            .collect();
+        assert_eq!(detections, vec![(73, 180)]);
+    }
+
+    /// Timing harness for the hard decision syncword search, not run by default:
+    /// `cargo test --release -- --ignored --nocapture bench`
+    ///
+    /// Both scanners see the same random bit stream with a syncword (with one bit
+    /// error every other time) every 10200 bits, the best of five runs is printed.
+    #[test]
+    #[ignore]
+    fn bench_packed_scan_vs_xcorr() {
+        const RUNS: usize = 5;
+        let mut rng = TestRng::new(1);
+        let syncword = hex_bits("1ACFFC1D").unwrap();
+        let mut bits = Vec::new();
+        for frame in 0..200 {
+            let mut marker = syncword.clone();
+            if frame % 2 == 1 {
+                marker[frame % 32] ^= true;
+            }
+            bits.extend(marker);
+            bits.extend((0..10200).map(|_| rng.next_bool()));
+        }
+
+        let best_of = |scan: &mut dyn FnMut(&[bool]) -> usize| {
+            (0..RUNS)
+                .map(|_| {
+                    let start = Instant::now();
+                    let hits = scan(black_box(&bits));
+                    (start.elapsed(), black_box(hits))
+                })
+                .min()
+                .unwrap()
+        };
+        let (xcorr_time, xcorr_hits) = best_of(&mut |bits| {
+            let mut xcorr = SyncwordScanXCorr::new(crate::SYNCWORD, 1);
+            bits.iter().filter(|bit| xcorr.tick(**bit)).count()
+        });
+        let (packed_time, packed_hits) = best_of(&mut |bits| {
+            let mut packed = PackedSyncwordScan::new(0x1ACFFC1D, 32, 1, PhaseAmbiguity::None);
+            bits.iter()
+                .filter(|bit| packed.tick(**bit).is_some())
+                .count()
+        });
+
+        assert!(xcorr_hits >= 200);
+        assert_eq!(xcorr_hits, packed_hits);
+        let rate = |time: Duration| bits.len() as f64 / time.as_secs_f64() / 1e6;
+        println!(
+            "{} bits, best of {}: SyncwordScanXCorr {:?} ({:.1} Mbit/s), PackedSyncwordScan {:?} ({:.1} Mbit/s)",
+            bits.len(),
+            RUNS,
+            xcorr_time,
+            rate(xcorr_time),
+            packed_time,
+            rate(packed_time)
+        );
     }
 }
@@ -19,3 +19,45 @@ pub fn create_output_directory(name: &str) -> PathBuf {
     ensure_exists!(std::fs::create_dir(&out_path));
     out_path
 }
+
+/// Unpacks bytes into bits, MSB first
+pub fn bytes_to_bits(bytes: &[u8]) -> Vec<bool> {
+    bytes
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |idx| (byte >> idx) & 1 != 0))
+        .collect()
+}
+
+/// Small deterministic xorshift generator, so tests don't need an extra dependency
+pub struct TestRng {
+    state: u64,
+}
+
+impl TestRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed.max(1) }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u64() >> 63 != 0
+    }
+
+    /// Uniform in [0, 1)
+    pub fn uniform(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Standard normal distributed value (Box-Muller)
+    pub fn gaussian(&mut self) -> f32 {
+        let u1 = 1.0 - self.uniform();
+        let u2 = self.uniform();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+    }
+}